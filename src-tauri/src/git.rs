@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::process::Command;
 
@@ -11,7 +12,9 @@ pub struct GitStatus {
     pub remote_url: Option<String>, // URL of the 'origin' remote
     pub changed_count: usize,
     pub ahead_count: i32, // -1 if no upstream tracking
+    pub behind_count: i32, // -1 if no upstream tracking; only as fresh as the last fetch
     pub current_branch: Option<String>,
+    pub staged_files: Vec<String>, // Paths relative to the notes folder
     pub error: Option<String>,
 }
 
@@ -23,6 +26,13 @@ pub struct GitResult {
     pub error: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitRemote {
+    pub url: String,
+    pub protocol: String, // "https", "http", "ssh" or "other" (local paths, file://, ...)
+}
+
 /// Check if git CLI is available
 pub fn is_available() -> bool {
     Command::new("git")
@@ -63,19 +73,7 @@ pub fn get_status(path: &Path) -> GitStatus {
         ..Default::default()
     };
 
-    // Get current branch
-    if let Ok(output) = Command::new("git")
-        .args(["branch", "--show-current"])
-        .current_dir(path)
-        .output()
-    {
-        if output.status.success() {
-            let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !branch.is_empty() {
-                status.current_branch = Some(branch);
-            }
-        }
-    }
+    status.current_branch = current_branch(path);
 
     // Check for remote
     if let Ok(output) = Command::new("git")
@@ -104,6 +102,8 @@ pub fn get_status(path: &Path) -> GitStatus {
         }
     }
 
+    status.staged_files = staged_files(path);
+
     // Get ahead count if we have a remote
     if status.has_remote && status.current_branch.is_some() {
         match Command::new("git")
@@ -118,6 +118,7 @@ pub fn get_status(path: &Path) -> GitStatus {
                     let parts: Vec<&str> = stdout.trim().split('\t').collect();
                     if parts.len() == 2 {
                         // parts[0] is behind count, parts[1] is ahead count
+                        status.behind_count = parts[0].parse().unwrap_or(0);
                         status.ahead_count = parts[1].parse().unwrap_or(0);
                     }
                 } else {
@@ -126,12 +127,14 @@ pub fn get_status(path: &Path) -> GitStatus {
                     if stderr.contains("no upstream") || stderr.contains("unknown revision") {
                         status.has_upstream = false;
                         status.ahead_count = -1; // Sentinel value indicating no upstream
+                        status.behind_count = -1;
                     }
                 }
             }
             Err(_) => {
                 status.has_upstream = false;
                 status.ahead_count = -1;
+                status.behind_count = -1;
             }
         }
     }
@@ -139,6 +142,152 @@ pub fn get_status(path: &Path) -> GitStatus {
     status
 }
 
+/// Get the checked-out branch (None for a detached HEAD or a failed git call)
+pub fn current_branch(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["branch", "--show-current"])
+        .current_dir(path)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if branch.is_empty() {
+        None
+    } else {
+        Some(branch)
+    }
+}
+
+/// List local branch names
+pub fn list_branches(path: &Path) -> Result<Vec<String>, String> {
+    let output = Command::new("git")
+        .args(["branch", "--format=%(refname:short)"])
+        .current_dir(path)
+        .output()
+        .map_err(|e| format!("Failed to run git branch: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Check out an existing local branch
+pub fn checkout_branch(path: &Path, branch: &str) -> GitResult {
+    // A leading dash would be parsed as an option
+    if branch.trim().is_empty() || branch.starts_with('-') {
+        return GitResult {
+            success: false,
+            message: None,
+            error: Some("Invalid branch name".to_string()),
+        };
+    }
+
+    // Only existing local branches; anything else could be taken as a path and
+    // overwrite uncommitted edits to that file
+    match list_branches(path) {
+        Ok(branches) if branches.iter().any(|b| b == branch) => {}
+        Ok(_) => {
+            return GitResult {
+                success: false,
+                message: None,
+                error: Some(format!("No local branch named {}", branch)),
+            };
+        }
+        Err(e) => {
+            return GitResult {
+                success: false,
+                message: None,
+                error: Some(e),
+            };
+        }
+    }
+
+    // The trailing "--" makes git treat the name as a branch, never a path
+    let output = Command::new("git")
+        .args(["checkout", branch, "--"])
+        .current_dir(path)
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                GitResult {
+                    success: true,
+                    message: Some(format!("Switched to branch {}", branch)),
+                    error: None,
+                }
+            } else {
+                GitResult {
+                    success: false,
+                    message: None,
+                    error: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                }
+            }
+        }
+        Err(e) => GitResult {
+            success: false,
+            message: None,
+            error: Some(format!("Failed to checkout: {}", e)),
+        },
+    }
+}
+
+/// Fetch from the default remote (hits the network)
+pub fn fetch(path: &Path) -> GitResult {
+    let output = Command::new("git")
+        .args(["fetch"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .current_dir(path)
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                GitResult {
+                    success: true,
+                    message: Some("Fetched".to_string()),
+                    error: None,
+                }
+            } else {
+                GitResult {
+                    success: false,
+                    message: None,
+                    error: Some(parse_push_error(&String::from_utf8_lossy(&output.stderr))),
+                }
+            }
+        }
+        Err(e) => GitResult {
+            success: false,
+            message: None,
+            error: Some(format!("Failed to fetch: {}", e)),
+        },
+    }
+}
+
+/// Fetch, then get the status so ahead/behind counts reflect the remote.
+/// A failed fetch is reported in `error` alongside the (possibly stale) counts.
+pub fn remote_status(path: &Path) -> GitStatus {
+    let mut status = get_status(path);
+    if !status.has_remote {
+        return status;
+    }
+
+    let fetched = fetch(path);
+    if fetched.success {
+        status = get_status(path);
+    } else {
+        status.error = fetched.error;
+    }
+    status
+}
+
 /// Stage all changes and commit
 pub fn commit_all(path: &Path, message: &str) -> GitResult {
     // Stage all changes
@@ -172,7 +321,99 @@ pub fn commit_all(path: &Path, message: &str) -> GitResult {
         };
     }
 
-    // Commit
+    commit_index(path, message)
+}
+
+/// Paths (relative to `path`) currently staged for the next commit
+pub fn staged_files(path: &Path) -> Vec<String> {
+    match Command::new("git")
+        .args(["diff", "--cached", "--name-only", "--relative", "-z"])
+        .current_dir(path)
+        .output()
+    {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Stage a single file (including its deletion)
+pub fn stage_file(path: &Path, file: &Path) -> GitResult {
+    let output = Command::new("git")
+        .args(["add", "-A", "--"])
+        .arg(file)
+        .current_dir(path)
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => GitResult {
+            success: true,
+            message: Some("File staged".to_string()),
+            error: None,
+        },
+        Ok(output) => GitResult {
+            success: false,
+            message: None,
+            error: Some(String::from_utf8_lossy(&output.stderr).to_string()),
+        },
+        Err(e) => GitResult {
+            success: false,
+            message: None,
+            error: Some(format!("Failed to run git add: {}", e)),
+        },
+    }
+}
+
+/// Remove a single file from the staged set, keeping its working tree changes
+pub fn unstage_file(path: &Path, file: &Path) -> GitResult {
+    // Before the first commit there is no HEAD to reset to, so drop it from the index instead
+    let has_head = Command::new("git")
+        .args(["rev-parse", "--verify", "-q", "HEAD"])
+        .current_dir(path)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    let args: &[&str] = if has_head {
+        &["reset", "-q", "--"]
+    } else {
+        &["rm", "--cached", "-q", "--"]
+    };
+
+    let output = Command::new("git")
+        .args(args)
+        .arg(file)
+        .current_dir(path)
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => GitResult {
+            success: true,
+            message: Some("File unstaged".to_string()),
+            error: None,
+        },
+        Ok(output) => GitResult {
+            success: false,
+            message: None,
+            error: Some(String::from_utf8_lossy(&output.stderr).to_string()),
+        },
+        Err(e) => GitResult {
+            success: false,
+            message: None,
+            error: Some(format!("Failed to unstage file: {}", e)),
+        },
+    }
+}
+
+/// Commit only what is already staged
+pub fn commit_staged(path: &Path, message: &str) -> GitResult {
+    commit_index(path, message)
+}
+
+/// Commit the current index
+fn commit_index(path: &Path, message: &str) -> GitResult {
     let commit_output = Command::new("git")
         .args(["commit", "-m", message])
         .current_dir(path)
@@ -214,8 +455,18 @@ pub fn commit_all(path: &Path, message: &str) -> GitResult {
 
 /// Push to remote
 pub fn push(path: &Path) -> GitResult {
+    run_push(path, &["push"], "Pushed successfully")
+}
+
+/// Push even if the remote branch has diverged (e.g. after an amend), but only if it is
+/// still where we last saw it, so others' commits are never overwritten
+pub fn push_force_with_lease(path: &Path) -> GitResult {
+    run_push(path, &["push", "--force-with-lease"], "Force-pushed successfully")
+}
+
+fn run_push(path: &Path, args: &[&str], success_message: &str) -> GitResult {
     let output = Command::new("git")
-        .args(["push"])
+        .args(args)
         .current_dir(path)
         .output();
 
@@ -224,7 +475,7 @@ pub fn push(path: &Path) -> GitResult {
             if output.status.success() {
                 GitResult {
                     success: true,
-                    message: Some("Pushed successfully".to_string()),
+                    message: Some(success_message.to_string()),
                     error: None,
                 }
             } else {
@@ -258,7 +509,57 @@ pub fn get_remote_url(path: &Path) -> Option<String> {
         .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
 }
 
-/// Add a remote named 'origin' with the given URL
+/// Get all configured remotes by name (empty if there are none)
+pub fn get_remotes(path: &Path) -> Result<BTreeMap<String, GitRemote>, String> {
+    let mut remotes = BTreeMap::new();
+    if !is_git_repo(path) {
+        return Ok(remotes);
+    }
+
+    let output = Command::new("git")
+        .args(["remote", "-v"])
+        .current_dir(path)
+        .output()
+        .map_err(|e| format!("Failed to list remotes: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    // Lines look like "origin\tgit@github.com:user/repo.git (fetch)"; a remote with a
+    // separate push URL still shows as the one to fetch from
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((name, rest)) = line.split_once('\t') else {
+            continue;
+        };
+        let Some(url) = rest.strip_suffix(" (fetch)") else {
+            continue;
+        };
+        remotes.insert(
+            name.to_string(),
+            GitRemote {
+                url: url.to_string(),
+                protocol: remote_protocol(url).to_string(),
+            },
+        );
+    }
+    Ok(remotes)
+}
+
+/// Classify a remote URL by transport, so the UI can hint at credential setup
+fn remote_protocol(url: &str) -> &'static str {
+    let url = url.trim();
+    if url.starts_with("https://") {
+        "https"
+    } else if url.starts_with("http://") {
+        "http"
+    } else if url.starts_with("ssh://") || url.starts_with("git@") {
+        "ssh"
+    } else {
+        "other"
+    }
+}
+
+/// Add a remote named 'origin' with the given URL, or update its URL if it exists
 pub fn add_remote(path: &Path, url: &str) -> GitResult {
     // Validate URL format (basic check)
     if !is_valid_remote_url(url) {
@@ -284,13 +585,9 @@ pub fn add_remote(path: &Path, url: &str) -> GitResult {
                 }
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                // Handle common case: remote already exists
+                // Remote already exists: point it at the new URL instead
                 if stderr.contains("already exists") {
-                    GitResult {
-                        success: false,
-                        message: None,
-                        error: Some("Remote 'origin' already exists".to_string()),
-                    }
+                    set_remote_url(path, "origin", url)
                 } else {
                     GitResult {
                         success: false,
@@ -308,6 +605,118 @@ pub fn add_remote(path: &Path, url: &str) -> GitResult {
     }
 }
 
+/// Change the URL of an existing remote
+pub fn set_remote_url(path: &Path, name: &str, url: &str) -> GitResult {
+    if !is_valid_remote_url(url) {
+        return GitResult {
+            success: false,
+            message: None,
+            error: Some("Invalid remote URL format. URL must start with https://, http://, or git@".to_string()),
+        };
+    }
+
+    let output = Command::new("git")
+        .args(["remote", "set-url", "--", name, url.trim()])
+        .current_dir(path)
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                GitResult {
+                    success: true,
+                    message: Some(format!("Remote '{}' updated", name)),
+                    error: None,
+                }
+            } else {
+                GitResult {
+                    success: false,
+                    message: None,
+                    error: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                }
+            }
+        }
+        Err(e) => GitResult {
+            success: false,
+            message: None,
+            error: Some(format!("Failed to update remote: {}", e)),
+        },
+    }
+}
+
+/// Remove a remote and its remote-tracking branches
+pub fn remove_remote(path: &Path, name: &str) -> GitResult {
+    let output = Command::new("git")
+        .args(["remote", "remove", "--", name])
+        .current_dir(path)
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                GitResult {
+                    success: true,
+                    message: Some(format!("Remote '{}' removed", name)),
+                    error: None,
+                }
+            } else {
+                GitResult {
+                    success: false,
+                    message: None,
+                    error: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                }
+            }
+        }
+        Err(e) => GitResult {
+            success: false,
+            message: None,
+            error: Some(format!("Failed to remove remote: {}", e)),
+        },
+    }
+}
+
+/// Clone a remote repository into `dest` (which must not exist or be empty)
+pub fn clone(url: &str, dest: &Path) -> GitResult {
+    if !is_valid_remote_url(url) {
+        return GitResult {
+            success: false,
+            message: None,
+            error: Some("Invalid remote URL format. URL must start with https://, http://, or git@".to_string()),
+        };
+    }
+
+    // No terminal to answer credential prompts; fail instead of hanging
+    let output = Command::new("git")
+        .args(["clone", "--"])
+        .arg(url.trim())
+        .arg(dest)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                GitResult {
+                    success: true,
+                    message: Some("Repository cloned".to_string()),
+                    error: None,
+                }
+            } else {
+                GitResult {
+                    success: false,
+                    message: None,
+                    error: Some(parse_push_error(&String::from_utf8_lossy(&output.stderr))),
+                }
+            }
+        }
+        Err(e) => GitResult {
+            success: false,
+            message: None,
+            error: Some(format!("Failed to clone: {}", e)),
+        },
+    }
+}
+
 /// Push to remote and set upstream tracking (git push -u origin <branch>)
 pub fn push_with_upstream(path: &Path, branch: &str) -> GitResult {
     let output = Command::new("git")
@@ -356,6 +765,10 @@ fn parse_push_error(stderr: &str) -> String {
         "Remote repository not found. Check the URL.".to_string()
     } else if stderr.contains("Could not resolve host") {
         "Could not connect to remote. Check your internet connection.".to_string()
+    } else if stderr.contains("stale info") {
+        "Push rejected: the remote branch changed since your last fetch. Fetch and review it before forcing.".to_string()
+    } else if stderr.contains("non-fast-forward") || stderr.contains("fetch first") {
+        "Push rejected: the remote has commits you don't have. Pull first, or force push with lease if you rewrote history.".to_string()
     } else {
         stderr.trim().to_string()
     }
@@ -0,0 +1,76 @@
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tantivy::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
+
+/// Token filter that replaces every term with a salted SHA-256 digest (hex, truncated),
+/// so the index's term dictionary doesn't contain the note's words. Queries go through
+/// the same analyzer, so whole-term matches still work; prefix and fuzzy matching don't.
+#[derive(Clone)]
+pub struct HashedTerms {
+    salt: Arc<[u8]>,
+}
+
+impl HashedTerms {
+    pub fn new(salt: &[u8]) -> Self {
+        Self { salt: salt.into() }
+    }
+}
+
+impl TokenFilter for HashedTerms {
+    type Tokenizer<T: Tokenizer> = HashedTermsFilter<T>;
+
+    fn transform<T: Tokenizer>(self, tokenizer: T) -> Self::Tokenizer<T> {
+        HashedTermsFilter {
+            tokenizer,
+            salt: self.salt,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HashedTermsFilter<T> {
+    tokenizer: T,
+    salt: Arc<[u8]>,
+}
+
+impl<T: Tokenizer> Tokenizer for HashedTermsFilter<T> {
+    type TokenStream<'a> = HashedTermsTokenStream<'a, T::TokenStream<'a>>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        HashedTermsTokenStream {
+            tail: self.tokenizer.token_stream(text),
+            salt: &self.salt,
+        }
+    }
+}
+
+pub struct HashedTermsTokenStream<'a, T> {
+    tail: T,
+    salt: &'a [u8],
+}
+
+// 16 bytes of digest keeps collisions negligible for a personal vault's vocabulary
+const DIGEST_HEX_LEN: usize = 32;
+
+impl<T: TokenStream> TokenStream for HashedTermsTokenStream<'_, T> {
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt);
+        hasher.update(self.tail.token().text.as_bytes());
+        let mut digest = format!("{:x}", hasher.finalize());
+        digest.truncate(DIGEST_HEX_LEN);
+        self.tail.token_mut().text = digest;
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
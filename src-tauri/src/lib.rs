@@ -2,13 +2,17 @@ use anyhow::Result;
 use base64::Engine;
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, Query, QueryParser};
 use tantivy::schema::*;
+use tantivy::tokenizer::{
+    Language, LowerCaser, NgramTokenizer, RemoveLongFilter, SimpleTokenizer, Stemmer,
+    StopWordFilter, TextAnalyzer, TextAnalyzerBuilder,
+};
 use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy};
 use tauri::{AppHandle, Emitter, Manager, State, WebviewUrl};
 use tauri::webview::WebviewWindowBuilder;
@@ -16,6 +20,8 @@ use tauri_plugin_clipboard_manager::ClipboardExt;
 use tokio::fs;
 
 mod git;
+mod hashed_terms;
+mod links;
 
 // Note metadata for list display
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +30,20 @@ pub struct NoteMetadata {
     pub title: String,
     pub preview: String,
     pub modified: i64,
+    // Full content, only filled in when list_notes is called with include_content
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub content: Option<String>,
+    // Millisecond mtime the title/preview were computed from (lets list_notes skip
+    // re-reading unchanged files)
+    #[serde(skip)]
+    pub modified_ms: i64,
+    // File size in bytes at that mtime; an edit within the mtime resolution that changes
+    // the length still invalidates the cached title/preview
+    #[serde(skip)]
+    pub size: u64,
+    // Inline and frontmatter tags, kept so suggest can offer tags without rereading notes
+    #[serde(skip)]
+    pub tags: Vec<String>,
 }
 
 // Full note content
@@ -34,6 +54,91 @@ pub struct Note {
     pub content: String,
     pub path: String,
     pub modified: i64,
+    // Millisecond mtime; pass back to save_note as expected_modified for conflict checks
+    #[serde(rename = "modifiedMs")]
+    pub modified_ms: i64,
+    // Set when the file isn't UTF-8 and was decoded from this encoding (e.g. "windows-1252").
+    // Saving writes UTF-8, so the UI should warn first (see convert_note_to_utf8).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    // Set when read_note was asked to leave the frontmatter out of `content`: the exact
+    // text removed from the start of the file, so `frontmatter + content` is the full note
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frontmatter: Option<String>,
+    // Per-note presentation from the frontmatter `scratch:` block (read_note only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<NoteOverrides>,
+    // "rtl" or "ltr" from the note's first strong directional character, or its
+    // textDirection override (read_note only). For when the textDirection setting is "auto".
+    #[serde(rename = "detectedDirection", skip_serializing_if = "Option::is_none")]
+    pub detected_direction: Option<String>,
+}
+
+// Presentation settings a note overrides for itself, e.g.
+//   scratch:
+//     font: monospace
+//     textDirection: rtl
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_direction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor_width: Option<String>,
+}
+
+// reload_note result: `changed` is true if the disk content differs from what the
+// editor was last given for this note (always true if it was never served)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadedNote {
+    pub note: Note,
+    pub changed: bool,
+}
+
+// Partial note content returned by read_note_range. Offsets are in bytes and always
+// fall on UTF-8 character boundaries; request the next chunk starting at `end`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRange {
+    pub id: String,
+    pub content: String,
+    pub start: usize,
+    pub end: usize,
+    pub total_len: usize,
+}
+
+// Errors from note commands that the frontend needs to tell apart.
+// Serialized as `{ kind: "conflict", ... }` / `{ kind: "other", message }`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum NoteError {
+    // The note changed on disk since the editor loaded it (diskModified is null if it was deleted).
+    // Both values are millisecond mtimes.
+    #[serde(rename_all = "camelCase")]
+    Conflict {
+        expected_modified: i64,
+        disk_modified: Option<i64>,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl From<String> for NoteError {
+    fn from(message: String) -> Self {
+        NoteError::Other { message }
+    }
+}
+
+impl From<&str> for NoteError {
+    fn from(message: &str) -> Self {
+        NoteError::Other {
+            message: message.to_string(),
+        }
+    }
 }
 
 // Theme color customization
@@ -80,12 +185,21 @@ pub struct EditorFontSettings {
     pub line_height: Option<f32>,         // default 1.6
 }
 
-// App config (stored in app data directory - just the notes folder path)
+// App config (stored in app data directory - notes folder path and recently used vaults)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
     pub notes_folder: Option<String>,
+    // Recently opened notes folders, most recent first
+    pub recent_vaults: Option<Vec<String>>,
+    // Absolute folder to keep the search index in instead of app data, e.g. a fast
+    // local disk when the vault is on a network share
+    #[serde(rename = "searchIndexLocation")]
+    pub search_index_location: Option<String>,
 }
 
+// Maximum number of entries kept in AppConfig::recent_vaults
+const MAX_RECENT_VAULTS: usize = 10;
+
 // Per-folder settings (stored in .scratch/settings.json within notes folder)
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Settings {
@@ -102,8 +216,107 @@ pub struct Settings {
     pub editor_width: Option<String>,
     #[serde(rename = "defaultNoteName")]
     pub default_note_name: Option<String>,
+    // Tantivy writer buffer in MB (default 50). Very small buffers flush more
+    // often and noticeably slow down full index rebuilds.
+    #[serde(rename = "indexWriterBufferMb")]
+    pub index_writer_buffer_mb: Option<u32>,
+    #[serde(rename = "lastOpenedNoteId")]
+    pub last_opened_note_id: Option<String>,
+    // Extra URL schemes open_url_safe may open (e.g. "obsidian", "vscode")
+    #[serde(rename = "allowedUrlSchemes")]
+    pub allowed_url_schemes: Option<Vec<String>>,
+    // Stemming language for search ("english" by default, "off" to disable stemming,
+    // "cjk" for Chinese/Japanese/Korean text that isn't separated by spaces)
+    #[serde(rename = "searchLanguage")]
+    pub search_language: Option<String>,
+    // Drop common words ("the", "and", ...) of the search language when indexing
+    #[serde(rename = "searchStopWords")]
+    pub search_stop_words: Option<bool>,
+    // Reject every command that would modify the vault (watching and search still work)
+    #[serde(rename = "readOnly")]
+    pub read_only: Option<bool>,
+    // Message used when git_commit is called with an empty message.
+    // Supports the note name tags plus {count} (number of changed files).
+    #[serde(rename = "commitMessageTemplate")]
+    pub commit_message_template: Option<String>,
+    // How long (ms) the file watcher collects external changes before sending one
+    // files-changed event to the frontend (default 300). Applies when the watcher restarts.
+    #[serde(rename = "watcherDebounceMs")]
+    pub watcher_debounce_ms: Option<u64>,
+    // How many folder levels below the notes folder are scanned (default 10, 1-50).
+    // Notes nested deeper don't appear in the list or search; raising it can slow scans.
+    #[serde(rename = "maxFolderDepth")]
+    pub max_folder_depth: Option<usize>,
+    // Scan into symlinked folders (e.g. a shared notes folder linked into the vault).
+    // Off by default; maxFolderDepth still applies and symlink loops are skipped. The file
+    // watcher doesn't follow symlinks on every platform, so outside edits to linked notes
+    // may only show up after a refresh or index rebuild.
+    #[serde(rename = "followSymlinks")]
+    pub follow_symlinks: Option<bool>,
+    // Keep note text out of the search index in app data: titles and content aren't
+    // stored, terms are salted hashes, and previews are read from the note files.
+    // Prefix and typo-tolerant matching stop working while this is on.
+    #[serde(rename = "encryptIndexContent")]
+    pub encrypt_index_content: Option<bool>,
+    // Hex salt for encryptIndexContent term hashes, generated when it's first enabled.
+    // Lives with the notes (not in app data) so the index alone can't be reversed.
+    #[serde(rename = "indexSalt")]
+    pub index_salt: Option<String>,
+    // Characters of body text shown as a note's preview in the list (default 100, 20-500)
+    #[serde(rename = "previewLength")]
+    pub preview_length: Option<usize>,
+    // Characters of the first line used as the title when a note has no "# " heading
+    // (default 50, 20-500). Changing it rebuilds the search index.
+    #[serde(rename = "titleFallbackLength")]
+    pub title_fallback_length: Option<usize>,
+    // Note quick_capture appends to, as an ID that may use the note name tags
+    // (default "{date}", a daily note). Created on first capture.
+    #[serde(rename = "captureNoteId")]
+    pub capture_note_id: Option<String>,
+    // How a note name that's already taken is made unique: "numeric" (default) appends
+    // -1, -2, ...; "short-hash" appends a short hash of the name, so the suffix doesn't
+    // depend on how many notes share it
+    #[serde(rename = "collisionStrategy")]
+    pub collision_strategy: Option<String>,
+    // Template apply_rename_all last renamed the notes with (e.g. "{date}-{title}").
+    // Saving a note named that way keeps the form and only swaps in the new title.
+    #[serde(rename = "renameTemplate")]
+    pub rename_template: Option<String>,
+    // Instructions sent to the AI CLI in place of the built-in wrapper, e.g. to localize
+    // it. `{file}` is replaced with the note's path and `{prompt}` with the user's prompt.
+    #[serde(rename = "aiSystemPrompt")]
+    pub ai_system_prompt: Option<String>,
+    // Largest note + prompt (bytes) sent to an AI CLI without allowLargeInput; 0 disables
+    #[serde(rename = "aiMaxInputBytes")]
+    pub ai_max_input_bytes: Option<u64>,
 }
 
+// Tantivy writer buffer bounds (MB). Tantivy needs at least 15MB per writer
+// thread and rejects arenas of 4GB or more.
+const DEFAULT_INDEX_WRITER_BUFFER_MB: usize = 50;
+const MIN_INDEX_WRITER_BUFFER_MB: usize = 15;
+const MAX_INDEX_WRITER_BUFFER_MB: usize = 4000;
+
+// Error returned by list_notes/read_note when the notes folder is configured but
+// missing (e.g. an unmounted drive), so the UI can tell it apart from an empty vault
+const VAULT_UNAVAILABLE: &str = "VaultUnavailable";
+
+// previewLength / titleFallbackLength defaults and bounds
+const DEFAULT_PREVIEW_LENGTH: usize = 100;
+const DEFAULT_TITLE_FALLBACK_LENGTH: usize = 50;
+const MIN_TEXT_LENGTH: usize = 20;
+const MAX_TEXT_LENGTH: usize = 500;
+
+// Folder scan depth bounds (maxFolderDepth)
+const DEFAULT_MAX_FOLDER_DEPTH: usize = 10;
+const MIN_MAX_FOLDER_DEPTH: usize = 1;
+const MAX_MAX_FOLDER_DEPTH: usize = 50;
+
+// Number of results returned by search_notes
+const SEARCH_LIMIT: usize = 20;
+// Candidates fetched from Tantivy before folder-scoped post-filtering
+const SCOPED_SEARCH_CANDIDATES: usize = 200;
+
 // Search result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -112,6 +325,238 @@ pub struct SearchResult {
     pub preview: String,
     pub modified: i64,
     pub score: f32,
+    // Notes folder the result came from (only set by search_all_vaults)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vault: Option<String>,
+}
+
+// A search_prefix hit: just enough to jump to the note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefixMatch {
+    pub id: String,
+    pub title: String,
+    pub score: f32,
+}
+
+// search_notes response. `degraded` is true when the Tantivy index is unavailable or
+// failing and results came from the slower substring scan (see repair_search_index).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    pub degraded: bool,
+}
+
+// A heading in note_outline. `line` is 1-based in the whole file, like TaskItem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineEntry {
+    pub level: usize,
+    pub text: String,
+    pub line: usize,
+}
+
+// A task list item found in a note. `line` is 1-based.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskItem {
+    pub note_id: String,
+    pub line: usize,
+    pub text: String,
+    pub checked: bool,
+}
+
+// Vault statistics for the dashboard
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStats {
+    pub note_count: usize,
+    pub word_count: usize,
+    pub asset_count: usize,
+    pub total_bytes: u64,
+}
+
+// How long vault_stats results are reused before rescanning
+const VAULT_STATS_TTL: Duration = Duration::from_secs(5);
+
+// Default for aiMaxInputBytes
+const DEFAULT_AI_MAX_INPUT_BYTES: u64 = 300 * 1024;
+
+// How long exit waits for the search index to commit before giving up
+const INDEX_FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+// How long ai_list_tools reuses its CLI probes
+const AI_TOOLS_TTL: Duration = Duration::from_secs(30);
+
+// Built-in AI backends as (name, command)
+const AI_TOOLS: &[(&str, &str)] = &[("Claude", "claude"), ("Codex", "codex")];
+
+// Notes sharing the same display title
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateTitleGroup {
+    pub title: String,
+    pub ids: Vec<String>,
+}
+
+// An autocomplete candidate from suggest: a note ID and its title for links, or a tag
+// (as both value and label) for tags
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub value: String,
+    pub label: String,
+}
+
+// Most candidates suggest returns
+const MAX_SUGGESTIONS: usize = 20;
+
+// term_frequency result: every (non-overlapping) hit, not just matching notes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermFrequency {
+    pub occurrences: usize,
+    pub note_count: usize,
+}
+
+// verify_index result: notes on disk the index doesn't know about, and index
+// entries whose file is gone. Either being non-empty means a rebuild is due.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexVerification {
+    pub missing_from_index: Vec<String>,
+    pub stale_in_index: Vec<String>,
+    pub in_sync: bool,
+}
+
+// get_frontmatter result. `data` is the YAML as a JSON object (null when the note has
+// no frontmatter or it doesn't parse); `error` is set when a block exists but isn't a
+// valid YAML mapping, in which case `raw` is the only copy of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Frontmatter {
+    pub data: Option<serde_json::Value>,
+    pub raw: Option<String>,
+    pub body_offset: usize,
+    pub error: bool,
+}
+
+// A note touched by rename_tag and how many tag occurrences in it were renamed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRenameChange {
+    pub id: String,
+    pub count: usize,
+}
+
+// A note ID change caused by a rename or move
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRename {
+    pub old_id: String,
+    pub new_id: String,
+}
+
+// A planned rename that was left undone, e.g. because the new name was already taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRename {
+    pub old_id: String,
+    pub new_id: String,
+    pub reason: String,
+}
+
+// Result of normalize_filenames and apply_rename_all
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameOutcome {
+    pub renamed: Vec<NoteRename>,
+    pub skipped: Vec<SkippedRename>,
+}
+
+// An image referenced by a note. `abs` is None for external (remote) images.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteAsset {
+    pub rel: String,
+    pub abs: Option<String>,
+    pub exists: bool,
+    pub external: bool,
+}
+
+// Vault diagnostics for the health panel
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultHealth {
+    pub notes_folder: Option<String>,
+    pub folder_exists: bool,
+    pub folder_writable: bool,
+    pub write_error: Option<String>,
+    pub index_present: bool,
+    pub index_doc_count: u64,
+    pub git_available: bool,
+    pub git_repo: bool,
+    pub note_count: usize,
+    pub broken_link_count: usize,
+    pub unused_asset_count: usize,
+}
+
+// link_graph result. Edges are deduplicated; a broken edge's `to` is the link target
+// as written, since it doesn't resolve to a note ID.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraph {
+    pub nodes: Vec<LinkGraphNode>,
+    pub edges: Vec<LinkGraphEdge>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraphNode {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraphEdge {
+    pub from: String,
+    pub to: String,
+    pub broken: bool,
+}
+
+// get_app_paths result: where the app keeps its own files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppPaths {
+    pub app_data_dir: AppPath,
+    pub config_file: AppPath,
+    pub search_index: AppPath,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppPath {
+    pub path: String,
+    pub exists: bool,
+}
+
+impl AppPath {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_string_lossy().into_owned(),
+            exists: path.exists(),
+        }
+    }
+}
+
+// Disk space for the filesystem holding the notes folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSpace {
+    pub available_bytes: u64,
+    pub total_bytes: u64,
 }
 
 // AI execution result
@@ -123,95 +568,287 @@ pub struct AiExecutionResult {
     pub error: Option<String>,
 }
 
+// One AI CLI run on a note, kept in the vault's AI history. `summary` is the first line
+// of the output (or error), not the full output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiHistoryEntry {
+    pub note_id: String,
+    pub tool: String,
+    pub prompt: String,
+    pub success: bool,
+    pub summary: String,
+    pub timestamp: i64,
+}
+
+// An AI edit made on a copy of a note: the CLI result plus a unified diff from the
+// note to the edited copy (empty if the CLI changed nothing)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiDiffPreview {
+    pub result: AiExecutionResult,
+    pub diff: String,
+}
+
+// An AI backend and whether its CLI was found on the expanded PATH
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiTool {
+    pub name: String,
+    pub command: String,
+    pub available: bool,
+}
+
 // File watcher state
 pub struct FileWatcherState {
     #[allow(dead_code)]
     watcher: RecommendedWatcher,
 }
 
+// Inverse of the most recent destructive operation (delete to trash, folder rename).
+// Every undoable operation is a path move, so undoing moves `from` back to `to`.
+#[derive(Debug, Clone)]
+pub struct UndoOperation {
+    pub description: String,
+    // Vault the operation happened in; undo is refused after switching vaults
+    pub notes_root: PathBuf,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+// Result of undo_last_operation: note IDs that went away and that came back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoResult {
+    pub description: String,
+    pub removed_ids: Vec<String>,
+    pub restored_ids: Vec<String>,
+}
+
 // Tantivy search index state
 pub struct SearchIndex {
     index: Index,
     reader: IndexReader,
-    writer: Mutex<IndexWriter>,
+    // None when opened read-only (another vault's index, see search_all_vaults)
+    writer: Option<Mutex<IndexWriter>>,
     #[allow(dead_code)]
     schema: Schema,
     id_field: Field,
     title_field: Field,
     content_field: Field,
+    aliases_field: Field,
     modified_field: Field,
+    // Set when title/content aren't stored (encryptIndexContent); search results then
+    // read them from the note files under this folder
+    source_root: Option<PathBuf>,
 }
 
 impl SearchIndex {
-    fn new(index_path: &PathBuf) -> Result<Self> {
-        // Build schema
+    fn build_schema(store_text: bool) -> Schema {
         let mut schema_builder = Schema::builder();
-        let id_field = schema_builder.add_text_field("id", STRING | STORED);
-        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
-        let content_field = schema_builder.add_text_field("content", TEXT | STORED);
-        let modified_field = schema_builder.add_i64_field("modified", INDEXED | STORED);
-        let schema = schema_builder.build();
+        let text_options = if store_text { TEXT | STORED } else { TEXT };
+        schema_builder.add_text_field("id", STRING | STORED);
+        schema_builder.add_text_field("title", text_options.clone());
+        schema_builder.add_text_field("content", text_options);
+        schema_builder.add_text_field("aliases", TEXT);
+        schema_builder.add_i64_field("modified", INDEXED | STORED);
+        schema_builder.build()
+    }
+
+    /// Open or create the on-disk index. `source_root` is the notes folder when note text
+    /// must not be stored in the index (see `source_root` on the struct).
+    fn new(
+        index_path: &PathBuf,
+        writer_buffer_bytes: usize,
+        analyzer: TextAnalyzer,
+        source_root: Option<PathBuf>,
+    ) -> Result<Self> {
+        let store_text = source_root.is_none();
+        let schema = Self::build_schema(store_text);
 
         // Create or open index
         std::fs::create_dir_all(index_path)?;
-        let index = Index::create_in_dir(index_path, schema.clone())
+        let mut index = Index::create_in_dir(index_path, schema.clone())
             .or_else(|_| Index::open_in_dir(index_path))?;
 
+        // An index from an older version, or one built before encryptIndexContent was
+        // switched, has a different schema; start over rather than keep plaintext around
+        // (or lose previews)
+        if index.schema() != schema {
+            drop(index);
+            std::fs::remove_dir_all(index_path)?;
+            std::fs::create_dir_all(index_path)?;
+            index = Index::create_in_dir(index_path, schema)?;
+        }
+
+        Self::from_index(index, Some(writer_buffer_bytes), analyzer, source_root)
+    }
+
+    /// Open an existing on-disk index for searching only. Takes no writer lock, so it
+    /// works for a vault another instance is writing to; fails if the index is missing
+    /// or was built with a different schema.
+    fn open_read_only(
+        index_path: &Path,
+        analyzer: TextAnalyzer,
+        source_root: Option<PathBuf>,
+    ) -> Result<Self> {
+        let index = Index::open_in_dir(index_path)?;
+        if index.schema() != Self::build_schema(source_root.is_none()) {
+            anyhow::bail!("Search index at {:?} is out of date", index_path);
+        }
+        Self::from_index(index, None, analyzer, source_root)
+    }
+
+    fn from_index(
+        index: Index,
+        writer_buffer_bytes: Option<usize>,
+        analyzer: TextAnalyzer,
+        source_root: Option<PathBuf>,
+    ) -> Result<Self> {
+        // Title and content use the "default" tokenizer; replacing it on the index means
+        // both indexing and the QueryParser go through the same analyzer
+        index.tokenizers().register("default", analyzer);
+
+        let schema = index.schema();
+        let id_field = schema.get_field("id")?;
+        let title_field = schema.get_field("title")?;
+        let content_field = schema.get_field("content")?;
+        let aliases_field = schema.get_field("aliases")?;
+        let modified_field = schema.get_field("modified")?;
+
         let reader = index
             .reader_builder()
             .reload_policy(ReloadPolicy::OnCommitWithDelay)
             .try_into()?;
 
-        let writer = index.writer(50_000_000)?; // 50MB buffer
+        let writer = match writer_buffer_bytes {
+            Some(bytes) => Some(Mutex::new(index.writer(bytes)?)),
+            None => None,
+        };
 
         Ok(Self {
             index,
             reader,
-            writer: Mutex::new(writer),
+            writer,
             schema,
             id_field,
             title_field,
             content_field,
+            aliases_field,
             modified_field,
+            source_root,
         })
     }
 
+    fn writer(&self) -> Result<std::sync::MutexGuard<'_, IndexWriter>> {
+        match self.writer {
+            Some(ref writer) => Ok(writer.lock().expect("search writer mutex")),
+            None => anyhow::bail!("Search index is open read-only"),
+        }
+    }
+
+    fn note_document(&self, id: &str, title: &str, content: &str, modified: i64) -> TantivyDocument {
+        let mut document = doc!(
+            self.id_field => id,
+            self.title_field => title,
+            self.content_field => content,
+            self.modified_field => modified,
+        );
+        for alias in note_aliases(content) {
+            document.add_text(self.aliases_field, &alias);
+        }
+        document
+    }
+
     fn index_note(&self, id: &str, title: &str, content: &str, modified: i64) -> Result<()> {
-        let mut writer = self.writer.lock().expect("search writer mutex");
+        let mut writer = self.writer()?;
 
         // Delete existing document with this ID
         let id_term = tantivy::Term::from_field_text(self.id_field, id);
         writer.delete_term(id_term);
 
         // Add new document
-        writer.add_document(doc!(
-            self.id_field => id,
-            self.title_field => title,
-            self.content_field => content,
-            self.modified_field => modified,
-        ))?;
+        writer.add_document(self.note_document(id, title, content, modified))?;
+
+        writer.commit()?;
+        Ok(())
+    }
+
+    fn doc_count(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
 
+    /// Commit anything the writer still holds, e.g. before the app exits
+    fn flush(&self) -> Result<()> {
+        let mut writer = self.writer()?;
         writer.commit()?;
         Ok(())
     }
 
+    /// Load the latest commit now rather than waiting for the reload policy
+    fn reload_reader(&self) -> Result<u64> {
+        self.reader.reload()?;
+        Ok(self.doc_count())
+    }
+
+    /// IDs of every live document, read from the doc store of each segment
+    fn indexed_ids(&self) -> Result<HashSet<String>> {
+        // Pick up commits the OnCommitWithDelay policy hasn't loaded yet
+        self.reader.reload()?;
+        let searcher = self.reader.searcher();
+
+        let mut ids = HashSet::new();
+        for segment_reader in searcher.segment_readers() {
+            let store_reader = segment_reader.get_store_reader(1)?;
+            for doc in store_reader.iter::<TantivyDocument>(segment_reader.alive_bitset()) {
+                if let Some(id) = doc?.get_first(self.id_field).and_then(|v| v.as_str()) {
+                    ids.insert(id.to_string());
+                }
+            }
+        }
+        Ok(ids)
+    }
+
     fn delete_note(&self, id: &str) -> Result<()> {
-        let mut writer = self.writer.lock().expect("search writer mutex");
+        let mut writer = self.writer()?;
         let id_term = tantivy::Term::from_field_text(self.id_field, id);
         writer.delete_term(id_term);
         writer.commit()?;
         Ok(())
     }
 
-    fn search(&self, query_str: &str, limit: usize) -> Result<Vec<SearchResult>> {
+    /// Search titles and content. Terms are OR'ed like the query parser default unless
+    /// `match_all` is set, in which case every whitespace-separated term must match.
+    fn search(
+        &self,
+        query_str: &str,
+        limit: usize,
+        match_all: bool,
+        lengths: TextLengths,
+    ) -> Result<Vec<SearchResult>> {
         let searcher = self.reader.searcher();
         let query_parser =
-            QueryParser::for_index(&self.index, vec![self.title_field, self.content_field]);
+            QueryParser::for_index(
+                &self.index,
+                vec![self.title_field, self.content_field, self.aliases_field],
+            );
 
         // Parse query, fall back to prefix query if parsing fails
-        let query = query_parser
-            .parse_query(query_str)
-            .or_else(|_| query_parser.parse_query(&format!("{}*", query_str)))?;
+        let parse_with_fallback = |text: &str| {
+            query_parser
+                .parse_query(text)
+                .or_else(|_| query_parser.parse_query(&format!("{}*", text)))
+        };
+
+        let query: Box<dyn Query> = if match_all {
+            let clauses = query_str
+                .split_whitespace()
+                .map(|term| parse_with_fallback(term).map(|q| (Occur::Must, q)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Box::new(BooleanQuery::new(clauses))
+        } else {
+            parse_with_fallback(query_str)?
+        };
 
         let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
 
@@ -225,23 +862,38 @@ impl SearchIndex {
                 .unwrap_or("")
                 .to_string();
 
-            let title = doc
-                .get_first(self.title_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-
-            let content = doc
-                .get_first(self.content_field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-
             let modified = doc
                 .get_first(self.modified_field)
                 .and_then(|v| v.as_i64())
                 .unwrap_or(0);
 
-            let preview = generate_preview(content);
+            let (title, preview) = match self.source_root {
+                Some(ref root) => {
+                    // Text isn't stored in the index; read it from the note
+                    let content = abs_path_from_id(root, &id)
+                        .ok()
+                        .and_then(|path| read_note_file(&path).ok());
+                    match content {
+                        Some(content) => (
+                            extract_title(&content, lengths.title_fallback),
+                            generate_preview(&content, lengths.preview),
+                        ),
+                        None => continue, // deleted since it was indexed
+                    }
+                }
+                None => {
+                    let title = doc
+                        .get_first(self.title_field)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let content = doc
+                        .get_first(self.content_field)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    (title, generate_preview(content, lengths.preview))
+                }
+            };
 
             results.push(SearchResult {
                 id,
@@ -249,56 +901,149 @@ impl SearchIndex {
                 preview,
                 modified,
                 score,
+                vault: None,
             });
         }
 
         Ok(results)
     }
 
-    fn rebuild_index(&self, notes_folder: &PathBuf) -> Result<()> {
-        let mut writer = self.writer.lock().expect("search writer mutex");
+    /// As-you-type title search: every word of `query` must match a title term, the last
+    /// one as a prefix ("meet" finds "Meeting notes"). Words of four or more characters
+    /// also tolerate one typo.
+    fn search_prefix(
+        &self,
+        query_str: &str,
+        limit: usize,
+        title_fallback_length: usize,
+    ) -> Result<Vec<PrefixMatch>> {
+        let mut analyzer = self.index.tokenizer_for_field(self.title_field)?;
+        let mut words = Vec::new();
+        let mut stream = analyzer.token_stream(query_str);
+        while stream.advance() {
+            words.push(stream.token().text.clone());
+        }
+        let Some(last) = words.len().checked_sub(1) else {
+            return Ok(Vec::new());
+        };
+
+        let clauses: Vec<(Occur, Box<dyn Query>)> = words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let term = tantivy::Term::from_field_text(self.title_field, word);
+                let distance = if word.chars().count() >= 4 { 1 } else { 0 };
+                let query: Box<dyn Query> = if i == last {
+                    Box::new(FuzzyTermQuery::new_prefix(term, distance, true))
+                } else {
+                    Box::new(FuzzyTermQuery::new(term, distance, true))
+                };
+                (Occur::Must, query)
+            })
+            .collect();
+        let query = BooleanQuery::new(clauses);
+
+        let searcher = self.reader.searcher();
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+
+        let mut matches = Vec::with_capacity(top_docs.len());
+        for (score, doc_address) in top_docs {
+            let doc: TantivyDocument = searcher.doc(doc_address)?;
+            let id = doc
+                .get_first(self.id_field)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let title = match self.source_root {
+                // Text isn't stored in the index; read it from the note
+                Some(ref root) => match abs_path_from_id(root, &id)
+                    .ok()
+                    .and_then(|path| read_note_file(&path).ok())
+                {
+                    Some(content) => extract_title(&content, title_fallback_length),
+                    None => continue, // deleted since it was indexed
+                },
+                None => doc
+                    .get_first(self.title_field)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+            };
+            matches.push(PrefixMatch { id, title, score });
+        }
+
+        Ok(matches)
+    }
+
+    /// Rebuild the index from scratch. When an app handle is given, emits
+    /// `index-progress` events every 100 files so the UI can show progress, and
+    /// `index-ready` once the rebuilt index is committed.
+    fn rebuild_index(
+        &self,
+        notes_folder: &Path,
+        scan: NoteScan,
+        title_fallback_length: usize,
+        app: Option<&AppHandle>,
+    ) -> Result<()> {
+        let mut writer = self.writer()?;
         writer.delete_all_documents()?;
+        let mut doc_count: u64 = 0;
 
         if notes_folder.exists() {
-            use walkdir::WalkDir;
-            for entry in WalkDir::new(notes_folder)
-                .max_depth(10)
-                .into_iter()
-                .filter_entry(is_visible_notes_entry)
-                .flatten()
-            {
-                let file_path = entry.path();
-                if !file_path.is_file() {
-                    continue;
+            // Cheap first pass: collect note entries without reading them so we know the total
+            let entries: Vec<(String, walkdir::DirEntry)> = walk_notes(notes_folder, scan).collect();
+            let total = entries.len();
+
+            for (processed, (id, entry)) in entries.into_iter().enumerate() {
+                if let Some(app) = app {
+                    if processed % 100 == 0 {
+                        let _ = app.emit("index-progress", IndexProgressEvent { processed, total });
+                    }
                 }
-                if let Some(id) = id_from_abs_path(notes_folder, file_path) {
-                    if let Ok(content) = std::fs::read_to_string(file_path) {
-                        let modified = entry
-                            .metadata()
-                            .ok()
-                            .and_then(|m| m.modified().ok())
-                            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                            .map(|d| d.as_secs() as i64)
-                            .unwrap_or(0);
 
-                        let title = extract_title(&content);
+                if let Ok(content) = read_note_file(entry.path()) {
+                    let modified = entry
+                        .metadata()
+                        .ok()
+                        .map(|m| mtime_secs(&m))
+                        .unwrap_or(0);
+
+                    let title = extract_title(&content, title_fallback_length);
 
-                        writer.add_document(doc!(
-                            self.id_field => id.as_str(),
-                            self.title_field => title,
-                            self.content_field => content.as_str(),
-                            self.modified_field => modified,
-                        ))?;
-                    }
+                    writer.add_document(self.note_document(&id, &title, &content, modified))?;
+                    doc_count += 1;
                 }
             }
+
+            if let Some(app) = app {
+                let _ = app.emit("index-progress", IndexProgressEvent { processed: total, total });
+            }
         }
 
         writer.commit()?;
+
+        // Best-effort: emitting without any open window is fine
+        if let Some(app) = app {
+            let _ = app.emit("index-ready", IndexReadyEvent { doc_count });
+        }
         Ok(())
     }
 }
 
+// Index rebuild finished event payload
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexReadyEvent {
+    doc_count: u64,
+}
+
+// Index rebuild progress event payload
+#[derive(Clone, Serialize)]
+struct IndexProgressEvent {
+    processed: usize,
+    total: usize,
+}
+
 // App state with improved structure
 pub struct AppState {
     pub app_config: RwLock<AppConfig>,  // notes_folder path (stored in app data)
@@ -307,6 +1052,13 @@ pub struct AppState {
     pub file_watcher: Mutex<Option<FileWatcherState>>,
     pub search_index: Mutex<Option<SearchIndex>>,
     pub debounce_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+    pub vault_stats_cache: Mutex<Option<(String, Instant, VaultStats)>>, // (folder, computed at, stats)
+    pub ai_tools_cache: Mutex<Option<(Instant, Vec<AiTool>)>>, // (probed at, tools)
+    pub last_operation: Mutex<Option<UndoOperation>>, // single-level undo buffer
+    pub served_hashes: Mutex<HashMap<String, String>>, // note ID -> hash of content last returned
+    pub preview_files: Mutex<HashSet<PathBuf>>, // canonical paths opened in preview windows
+    pub preview_watchers: Mutex<HashMap<String, FileWatcherState>>, // preview window label -> watcher for its file
+    pub self_writes: Mutex<HashMap<PathBuf, Instant>>, // paths the app just wrote, for the watcher to skip
 }
 
 impl Default for AppState {
@@ -318,10 +1070,66 @@ impl Default for AppState {
             file_watcher: Mutex::new(None),
             search_index: Mutex::new(None),
             debounce_map: Arc::new(Mutex::new(HashMap::new())),
+            vault_stats_cache: Mutex::new(None),
+            ai_tools_cache: Mutex::new(None),
+            last_operation: Mutex::new(None),
+            served_hashes: Mutex::new(HashMap::new()),
+            preview_files: Mutex::new(HashSet::new()),
+            preview_watchers: Mutex::new(HashMap::new()),
+            self_writes: Mutex::new(HashMap::new()),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CollisionStrategy {
+    Numeric,
+    ShortHash,
+}
+
+fn collision_strategy(state: &AppState) -> CollisionStrategy {
+    let settings = state.settings.read().expect("settings read lock");
+    match settings.collision_strategy.as_deref() {
+        Some("short-hash") => CollisionStrategy::ShortHash,
+        _ => CollisionStrategy::Numeric,
+    }
+}
+
+/// Suffix for the `attempt`th (1-based) try at a free name: the attempt number, or a
+/// hash of `seed` (usually the note's content) that gets longer on each retry
+fn collision_suffix(strategy: CollisionStrategy, seed: &str, attempt: usize) -> String {
+    match strategy {
+        CollisionStrategy::Numeric => attempt.to_string(),
+        CollisionStrategy::ShortHash => {
+            let hash = content_hash(seed.as_bytes());
+            let len = 6 + 2 * (attempt - 1);
+            if len <= hash.len() {
+                hash[..len].to_string()
+            } else {
+                format!("{}-{}", hash, attempt)
+            }
+        }
+    }
+}
+
+// Whether `leaf` is `base` plus a suffix collision_suffix could have added ("-2",
+// "-a1b2c3", "-a1b2c3d4-5"), i.e. a name that's already a unique version of `base`
+fn is_collision_variant(leaf: &str, base: &str) -> bool {
+    let Some(suffix) = leaf.strip_prefix(base).and_then(|rest| rest.strip_prefix('-')) else {
+        return false;
+    };
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if is_number(suffix) {
+        return true;
+    }
+    let hash = match suffix.split_once('-') {
+        Some((hash, attempt)) if is_number(attempt) => hash,
+        Some(_) => return false,
+        None => suffix,
+    };
+    hash.len() >= 6 && hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+}
+
 // Utility: Sanitize filename from title
 fn sanitize_filename(title: &str) -> String {
     let sanitized: String = title
@@ -367,6 +1175,22 @@ fn expand_note_name_template(template: &str) -> String {
     result
 }
 
+const DEFAULT_COMMIT_MESSAGE_TEMPLATE: &str = "Update notes {date} {time}";
+
+fn commit_message_template(state: &AppState) -> String {
+    let settings = state.settings.read().expect("settings read lock");
+    settings
+        .commit_message_template
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_COMMIT_MESSAGE_TEMPLATE.to_string())
+}
+
+/// Expands a commit message template: the note name tags plus {count}
+fn expand_commit_message_template(template: &str, changed_count: usize) -> String {
+    expand_note_name_template(template).replace("{count}", &changed_count.to_string())
+}
+
 /// Extracts a display title from a note ID (filename)
 fn extract_title_from_id(id: &str) -> String {
     // Get last path component (filename)
@@ -397,25 +1221,39 @@ fn is_effectively_empty(s: &str) -> bool {
 
 /// Strip YAML frontmatter (leading `---` ... `---` block) from content.
 fn strip_frontmatter(content: &str) -> &str {
-    let trimmed = content.trim_start();
-    if trimmed.starts_with("---") {
-        // Find the closing --- (skip the opening line)
-        if let Some(rest) = trimmed.strip_prefix("---") {
-            if let Some(end) = rest.find("\n---") {
-                // Skip past closing --- and the newline after it (handle CRLF)
-                let after_close = &rest[end + 4..];
-                return after_close
-                    .strip_prefix("\r\n")
-                    .or_else(|| after_close.strip_prefix('\n'))
-                    .unwrap_or(after_close);
-            }
-        }
+    match split_frontmatter(content) {
+        Some((_, body_offset)) => &content[body_offset..],
+        None => content,
     }
-    content
+}
+
+/// Locate a leading `---` frontmatter block. Returns the raw YAML between the fences
+/// and the byte offset in `content` where the body starts.
+fn split_frontmatter(content: &str) -> Option<(&str, usize)> {
+    let trimmed = content.trim_start();
+    // Find the closing --- (skip the opening line)
+    let rest = trimmed.strip_prefix("---")?;
+    let end = rest.find("\n---")?;
+
+    // Skip past closing --- and the newline after it (handle CRLF)
+    let after_close = &rest[end + 4..];
+    let body = after_close
+        .strip_prefix("\r\n")
+        .or_else(|| after_close.strip_prefix('\n'))
+        .unwrap_or(after_close);
+
+    let yaml = &rest[..end];
+    let yaml = yaml
+        .strip_prefix("\r\n")
+        .or_else(|| yaml.strip_prefix('\n'))
+        .unwrap_or(yaml)
+        .trim_end_matches('\r');
+    Some((yaml, content.len() - body.len()))
 }
 
 // Utility: Extract title from markdown content
-fn extract_title(content: &str) -> String {
+// (a first line that isn't a heading is cut to `fallback_length` characters)
+fn extract_title(content: &str, fallback_length: usize) -> String {
     let body = strip_frontmatter(content);
     for line in body.lines() {
         let trimmed = line.trim();
@@ -426,14 +1264,14 @@ fn extract_title(content: &str) -> String {
             }
         }
         if !is_effectively_empty(trimmed) {
-            return trimmed.chars().take(50).collect();
+            return trimmed.chars().take(fallback_length).collect();
         }
     }
     "Untitled".to_string()
 }
 
 // Utility: Generate preview from content (strip markdown formatting)
-fn generate_preview(content: &str) -> String {
+fn generate_preview(content: &str, length: usize) -> String {
     let body = strip_frontmatter(content);
     // Skip the first line (title), find first non-empty line
     for line in body.lines().skip(1) {
@@ -441,13 +1279,54 @@ fn generate_preview(content: &str) -> String {
         if !trimmed.is_empty() {
             let stripped = strip_markdown(trimmed);
             if !stripped.is_empty() {
-                return stripped.chars().take(100).collect();
+                return stripped.chars().take(length).collect();
             }
         }
     }
     String::new()
 }
 
+// Utility: Parse a task list line ("- [ ] text", "* [x] text", indented or not).
+// Returns (checked, text after the marker).
+fn parse_task_line(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start();
+    let rest = rest
+        .strip_prefix("- ")
+        .or_else(|| rest.strip_prefix("* "))
+        .or_else(|| rest.strip_prefix("+ "))?;
+    let (checked, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+        (true, text)
+    } else {
+        return None;
+    };
+    // The marker must be followed by whitespace (or end the line)
+    if !text.is_empty() && !text.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((checked, text.trim()))
+}
+
+// Utility: Task list lines in `content` outside fenced code blocks, as
+// (0-based line index, checked, text). Shared by list_tasks and toggle_task so
+// both agree on which lines are tasks.
+fn task_lines(content: &str) -> impl Iterator<Item = (usize, bool, &str)> {
+    let fences = fenced_code_ranges(content);
+    let mut offset = 0;
+    content
+        .split_inclusive('\n')
+        .enumerate()
+        .filter_map(move |(index, line)| {
+            let line_start = offset;
+            offset += line.len();
+            if fences.iter().any(|r| r.contains(&line_start)) {
+                return None;
+            }
+            parse_task_line(line).map(|(checked, text)| (index, checked, text))
+        })
+}
+
 // Strip common markdown formatting from text
 fn strip_markdown(text: &str) -> String {
     let mut result = text.to_string();
@@ -533,10 +1412,9 @@ fn strip_markdown(text: &str) -> String {
     }
 
     // Remove task list markers
-    result = result
-        .replace("- [ ] ", "")
-        .replace("- [x] ", "")
-        .replace("- [X] ", "");
+    if let Some((_, text)) = parse_task_line(&result) {
+        result = text.to_string();
+    }
 
     // Remove list markers at start (-, *, +, 1.)
     let list_re = regex::Regex::new(r"^(\s*[-+*]|\s*\d+\.)\s+").unwrap();
@@ -545,6 +1423,138 @@ fn strip_markdown(text: &str) -> String {
     result.trim().to_string()
 }
 
+/// Extract image references from markdown `![alt](src)` and inline HTML `<img src="...">`,
+/// in document order without duplicates.
+fn extract_image_refs(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    image_ref_spans(content)
+        .into_iter()
+        .map(|(_, src)| src)
+        .filter(|src| seen.insert(src.clone()))
+        .collect()
+}
+
+/// Every image reference in document order: the byte range of the src as written
+/// (including any angle brackets) and the src without them
+fn image_ref_spans(content: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let md_re = regex::Regex::new(r#"!\[[^\]]*\]\(\s*(<[^>]+>|[^)\s]+)"#).unwrap();
+    let html_re = regex::Regex::new(r#"(?i)<img\b[^>]*?\bsrc\s*=\s*["']([^"']+)["']"#).unwrap();
+
+    let mut found: Vec<(std::ops::Range<usize>, String)> = md_re
+        .captures_iter(content)
+        .chain(html_re.captures_iter(content))
+        .filter_map(|caps| {
+            let m = caps.get(1)?;
+            let src = m.as_str().trim_start_matches('<').trim_end_matches('>');
+            Some((m.range(), src.to_string()))
+        })
+        .collect();
+    found.sort_by_key(|(range, _)| range.start);
+    found
+}
+
+/// Byte ranges of fenced code blocks (``` or ~~~), including the fence lines.
+/// An unclosed fence runs to the end of the content.
+fn fenced_code_ranges(content: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut open: Option<(usize, &str)> = None; // (start offset, fence marker)
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker = if trimmed.starts_with("```") {
+            Some("```")
+        } else if trimmed.starts_with("~~~") {
+            Some("~~~")
+        } else {
+            None
+        };
+
+        match (open, marker) {
+            (None, Some(m)) => open = Some((offset, m)),
+            (Some((start, m)), Some(closing)) if m == closing => {
+                ranges.push(start..offset + line.len());
+                open = None;
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    if let Some((start, _)) = open {
+        ranges.push(start..content.len());
+    }
+    ranges
+}
+
+// Utility: URL the webview can load a local file from (same as convertFileSrc on the frontend)
+fn asset_protocol_url(path: &Path) -> String {
+    let encoded = urlencoding::encode(&path.to_string_lossy()).into_owned();
+    if cfg!(windows) {
+        format!("http://asset.localhost/{}", encoded)
+    } else {
+        format!("asset://localhost/{}", encoded)
+    }
+}
+
+// Utility: Render markdown to HTML with GFM extensions. Raw HTML is escaped rather than
+// passed through and script-capable link schemes are dropped, so the output is safe to
+// inject into the webview. With `notes_root` and `note_id`, local image paths are
+// rewritten to asset protocol URLs.
+fn render_markdown_html(content: &str, notes_root: Option<&Path>, note_id: Option<&str>) -> String {
+    use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+    let is_unsafe_url = |url: &str| {
+        let lower = url.trim().to_ascii_lowercase();
+        ["javascript:", "vbscript:", "file:"]
+            .iter()
+            .any(|scheme| lower.starts_with(scheme))
+    };
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+
+    let events = Parser::new_ext(strip_frontmatter(content), options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+            let dest_url = if is_unsafe_url(&dest_url) { CowStr::from("#") } else { dest_url };
+            Event::Start(Tag::Link { link_type, dest_url, title, id })
+        }
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            let is_data_text = dest_url.to_ascii_lowercase().starts_with("data:text");
+            let dest_url = if is_unsafe_url(&dest_url) || is_data_text {
+                CowStr::from("")
+            } else if is_external_ref(&dest_url) {
+                dest_url
+            } else {
+                match (notes_root, note_id) {
+                    (Some(root), Some(id)) => resolve_note_relative_path(root, id, &dest_url)
+                        .map(|path| CowStr::from(asset_protocol_url(&path)))
+                        .unwrap_or(dest_url),
+                    _ => dest_url,
+                }
+            };
+            Event::Start(Tag::Image { link_type, dest_url, title, id })
+        }
+        other => other,
+    });
+
+    let mut output = String::with_capacity(content.len() * 3 / 2);
+    html::push_html(&mut output, events);
+    output
+}
+
+// Utility: Check if a link/image target points outside the vault (http, data URIs, etc.)
+fn is_external_ref(target: &str) -> bool {
+    let lower = target.to_ascii_lowercase();
+    lower.starts_with("http://")
+        || lower.starts_with("https://")
+        || lower.starts_with("data:")
+        || lower.starts_with("//")
+}
+
 /// Filter for WalkDir: skips dot-directories (e.g. .scratch, .git) and assets/.
 fn is_visible_notes_entry(entry: &walkdir::DirEntry) -> bool {
     if entry.file_type().is_dir() {
@@ -554,6 +1564,142 @@ fn is_visible_notes_entry(entry: &walkdir::DirEntry) -> bool {
     true
 }
 
+/// Walk the notes folder and yield `(note ID, entry)` for every visible markdown note
+/// up to maxFolderDepth folder levels below the root, following symlinked folders
+/// if `scan.follow_symlinks` is set. WalkDir reports symlink loops as errors, which are
+/// skipped.
+fn walk_notes(
+    notes_root: &Path,
+    scan: NoteScan,
+) -> impl Iterator<Item = (String, walkdir::DirEntry)> + '_ {
+    walkdir::WalkDir::new(notes_root)
+        .max_depth(scan.max_depth)
+        .follow_links(scan.follow_symlinks)
+        .into_iter()
+        .filter_entry(is_visible_notes_entry)
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(move |entry| id_from_abs_path(notes_root, entry.path()).map(|id| (id, entry)))
+}
+
+// Hex SHA-256 of a note's raw bytes; stable across platforms for sync clients
+fn hash_note_file(path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(content_hash(&bytes))
+}
+
+fn content_hash(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// Remember the content most recently handed to the editor for a note
+fn remember_served_content(state: &AppState, id: &str, content: &str) {
+    let mut served = state.served_hashes.lock().expect("served hashes mutex");
+    served.insert(id.to_string(), content_hash(content.as_bytes()));
+}
+
+// Read every note in the vault as (id, content). Unreadable files are skipped.
+fn load_vault_notes(notes_root: &Path, scan: NoteScan) -> Vec<(String, String)> {
+    walk_notes(notes_root, scan)
+        .filter_map(|(id, entry)| {
+            read_note_file(entry.path())
+                .ok()
+                .map(|content| (id, content))
+        })
+        .collect()
+}
+
+// Utility: Count whitespace-separated words in a note body (frontmatter excluded)
+fn count_words(content: &str) -> usize {
+    strip_frontmatter(content).split_whitespace().count()
+}
+
+/// Inline #tags in a note body, lowercased and deduplicated in order of appearance.
+/// A tag starts with `#` at the start of a line or after whitespace and must contain a
+/// letter (so "# Heading" and "#123" aren't tags). Fenced and inline code are skipped.
+fn extract_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for span in inline_tag_spans(content) {
+        let tag = content[span].to_lowercase();
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Inline tags plus frontmatter `tags:` (a list, or one string of comma- or
+/// space-separated tags), lowercased and deduplicated. Frontmatter tags come first.
+fn note_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let fields = split_frontmatter(content).and_then(|(raw, _)| parse_frontmatter_yaml(raw));
+    let values: Vec<&str> = match fields.as_ref().and_then(|fields| fields.get("tags")) {
+        Some(serde_json::Value::Array(values)) => values
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .collect(),
+        Some(serde_json::Value::String(value)) => value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let frontmatter = values
+        .into_iter()
+        .filter_map(|value| normalize_tag_name(value).ok());
+    for tag in frontmatter.chain(extract_tags(content)) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Byte ranges in `content` of each inline tag's name (after the `#`), in order.
+/// See extract_tags for what counts as a tag.
+fn inline_tag_spans(content: &str) -> Vec<std::ops::Range<usize>> {
+    let body_offset = content.len() - strip_frontmatter(content).len();
+    let body = &content[body_offset..];
+    let fences = fenced_code_ranges(body);
+    let mut spans = Vec::new();
+    let mut offset = 0;
+
+    for line in body.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if fences.iter().any(|r| r.contains(&line_start)) {
+            continue;
+        }
+
+        let mut in_code = false;
+        let mut prev: Option<char> = None;
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if c == '#' && !in_code && prev.is_none_or(char::is_whitespace) {
+                let rest = &line[i + 1..];
+                let len = rest
+                    .find(|ch: char| !(ch.is_alphanumeric() || ch == '-' || ch == '_' || ch == '/'))
+                    .unwrap_or(rest.len());
+                let tag = rest[..len].trim_end_matches('/');
+                if tag.chars().any(char::is_alphabetic) {
+                    let start = body_offset + line_start + i + 1;
+                    spans.push(start..start + tag.len());
+                }
+                while chars.peek().is_some_and(|(j, _)| *j < i + 1 + len) {
+                    chars.next();
+                }
+                prev = Some('#');
+                continue;
+            }
+            prev = Some(c);
+        }
+    }
+    spans
+}
+
 /// Convert an absolute file path to a note ID (relative path from notes root, no .md extension, POSIX separators).
 /// Returns None if the path is outside the root, not a .md file, or in an excluded directory.
 fn id_from_abs_path(notes_root: &Path, file_path: &Path) -> Option<String> {
@@ -624,170 +1770,3514 @@ fn abs_path_from_id(notes_root: &Path, id: &str) -> Result<PathBuf, String> {
     Ok(file_path)
 }
 
-// Get app config file path (in app data directory)
-fn get_app_config_path(app: &AppHandle) -> Result<PathBuf> {
-    let app_data = app.path().app_data_dir()?;
-    std::fs::create_dir_all(&app_data)?;
-    Ok(app_data.join("config.json"))
-}
+/// Validate a vault-relative folder path (e.g. "work/projects") against path traversal.
+/// Returns the folder without trailing slashes.
+fn validate_folder_id(folder: &str) -> Result<String, String> {
+    let trimmed = folder.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return Err("Invalid folder: path is empty".to_string());
+    }
+    if trimmed.contains('\\') {
+        return Err("Invalid folder: backslashes not allowed".to_string());
+    }
 
-// Get per-folder settings file path (in .scratch/ within notes folder)
-fn get_settings_path(notes_folder: &str) -> PathBuf {
-    let scratch_dir = PathBuf::from(notes_folder).join(".scratch");
-    std::fs::create_dir_all(&scratch_dir).ok();
-    scratch_dir.join("settings.json")
-}
+    for component in Path::new(trimmed).components() {
+        match component {
+            std::path::Component::ParentDir => {
+                return Err("Invalid folder: parent directory references not allowed".to_string());
+            }
+            std::path::Component::CurDir => {
+                return Err("Invalid folder: current directory references not allowed".to_string());
+            }
+            std::path::Component::RootDir | std::path::Component::Prefix(_) => {
+                return Err("Invalid folder: absolute paths not allowed".to_string());
+            }
+            _ => {}
+        }
+    }
 
-// Get search index path
-fn get_search_index_path(app: &AppHandle) -> Result<PathBuf> {
-    let app_data = app.path().app_data_dir()?;
-    std::fs::create_dir_all(&app_data)?;
-    Ok(app_data.join("search_index"))
+    Ok(trimmed.to_string())
 }
 
-// Load app config from disk (notes folder path)
-fn load_app_config(app: &AppHandle) -> AppConfig {
-    let path = match get_app_config_path(app) {
-        Ok(p) => p,
-        Err(_) => return AppConfig::default(),
-    };
+/// Resolve a path referenced from a note (e.g. an image src) to an absolute path inside the
+/// vault. `assets/...` is relative to the vault root; anything else is relative to the
+/// note's folder. Resolution is lexical, so the target does not need to exist.
+fn resolve_note_relative_path(notes_root: &Path, note_id: &str, rel: &str) -> Result<PathBuf, String> {
+    // Validates the note ID itself
+    let note_path = abs_path_from_id(notes_root, note_id)?;
 
-    if path.exists() {
-        std::fs::read_to_string(&path)
-            .ok()
-            .and_then(|content| serde_json::from_str(&content).ok())
-            .unwrap_or_default()
-    } else {
-        AppConfig::default()
-    }
-}
+    let decoded = urlencoding::decode(rel.trim())
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| rel.trim().to_string());
+    let rel_path = Path::new(decoded.trim_start_matches("./"));
 
-// Save app config to disk
-fn save_app_config(app: &AppHandle, config: &AppConfig) -> Result<()> {
-    let path = get_app_config_path(app)?;
-    let content = serde_json::to_string_pretty(config)?;
-    std::fs::write(path, content)?;
-    Ok(())
-}
+    let mut resolved = if decoded.starts_with("assets/") {
+        notes_root.to_path_buf()
+    } else {
+        note_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| notes_root.to_path_buf())
+    };
 
-// Load per-folder settings from disk
-fn load_settings(notes_folder: &str) -> Settings {
-    let path = get_settings_path(notes_folder);
+    for component in rel_path.components() {
+        match component {
+            std::path::Component::Normal(part) => resolved.push(part),
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                if resolved == notes_root || !resolved.pop() {
+                    return Err("Path escapes notes folder".to_string());
+                }
+            }
+            std::path::Component::RootDir | std::path::Component::Prefix(_) => {
+                return Err("Absolute paths not allowed".to_string());
+            }
+        }
+    }
 
-    if path.exists() {
-        std::fs::read_to_string(&path)
-            .ok()
-            .and_then(|content| serde_json::from_str(&content).ok())
-            .unwrap_or_default()
-    } else {
-        Settings::default()
+    if !resolved.starts_with(notes_root) {
+        return Err("Path escapes notes folder".to_string());
     }
-}
 
-// Save per-folder settings to disk
-fn save_settings(notes_folder: &str, settings: &Settings) -> Result<()> {
-    let path = get_settings_path(notes_folder);
-    let content = serde_json::to_string_pretty(settings)?;
-    std::fs::write(path, content)?;
-    Ok(())
+    Ok(resolved)
 }
 
-// Clean up old entries from debounce map (entries older than 5 seconds)
-fn cleanup_debounce_map(map: &Mutex<HashMap<PathBuf, Instant>>) {
-    let mut map = map.lock().expect("debounce map mutex");
-    let now = Instant::now();
-    map.retain(|_, last| now.duration_since(*last) < Duration::from_secs(5));
+// Utility: Check whether a vault-relative folder is hidden (dot-dir) or the assets dir
+fn is_reserved_folder(folder: &str) -> bool {
+    folder
+        .split('/')
+        .any(|part| part.starts_with('.') || part == "assets")
 }
 
-// Normalize notes folder path from plain paths and legacy file:// URIs.
-fn normalize_notes_folder_path(path: &str) -> Result<PathBuf, String> {
-    let trimmed = path.trim();
-    if trimmed.is_empty() {
-        return Err("Notes folder path is empty".to_string());
+// Move a vault-relative file or folder into .scratch/trash/<timestamp>/, preserving its
+// relative path. Returns the location it was moved to.
+fn move_to_trash(notes_root: &Path, rel: &str) -> Result<PathBuf, String> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let trash_root = notes_root.join(".scratch").join("trash");
+
+    let mut batch_dir = trash_root.join(timestamp.to_string());
+    let mut counter = 1;
+    while batch_dir.join(rel).exists() {
+        batch_dir = trash_root.join(format!("{}-{}", timestamp, counter));
+        counter += 1;
     }
 
-    if trimmed.starts_with("file://") {
-        let parsed = url::Url::parse(trimmed)
-            .map_err(|e| format!("Invalid file URL for notes folder: {}", e))?;
-        return parsed
-            .to_file_path()
-            .map_err(|_| "Invalid file URL for notes folder".to_string());
+    let target = batch_dir.join(rel);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
+    std::fs::rename(notes_root.join(rel), &target)
+        .map_err(|e| format!("Failed to move to trash: {}", e))?;
 
-    Ok(PathBuf::from(trimmed))
+    Ok(target)
 }
 
-// TAURI COMMANDS
+// Fail early with a clear message when the vault is opened read-only or another
+// instance holds its lock
+fn ensure_writable(state: &AppState) -> Result<(), String> {
+    {
+        let settings = state.settings.read().expect("settings read lock");
+        if settings.read_only.unwrap_or(false) {
+            return Err("Vault is read-only".to_string());
+        }
+    }
+    ensure_vault_lock(state)
+}
 
-#[tauri::command]
-fn get_notes_folder(state: State<AppState>) -> Option<String> {
-    state
+// Refuse writes while another instance holds the current vault's lock. Taking over
+// through set_notes_folder with `force_takeover` lifts this.
+fn ensure_vault_lock(state: &AppState) -> Result<(), String> {
+    let folder = state
         .app_config
         .read()
         .expect("app_config read lock")
         .notes_folder
-        .clone()
+        .clone();
+    match folder.as_deref().and_then(read_vault_lock) {
+        Some(holder) if holder.pid != std::process::id() && lock_held_by_other(&holder) => {
+            Err(held_by_other_error(&holder))
+        }
+        _ => Ok(()),
+    }
 }
 
-#[tauri::command]
-fn set_notes_folder(app: AppHandle, path: String, state: State<AppState>) -> Result<(), String> {
-    let path_buf = normalize_notes_folder_path(&path)?;
-    let normalized_path = path_buf.to_string_lossy().into_owned();
+// Remember how to undo the operation that just moved `to` -> `from`
+fn record_undo(state: &AppState, description: String, from: PathBuf, to: PathBuf) {
+    let notes_root = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        match app_config.notes_folder {
+            Some(ref folder) => PathBuf::from(folder),
+            None => return,
+        }
+    };
+    let mut last = state.last_operation.lock().expect("last operation mutex");
+    *last = Some(UndoOperation {
+        description,
+        notes_root,
+        from,
+        to,
+    });
+}
 
-    // Verify it's a valid directory
-    if !path_buf.exists() {
-        std::fs::create_dir_all(&path_buf).map_err(|e| e.to_string())?;
+fn clear_undo(state: &AppState) {
+    state.last_operation.lock().expect("last operation mutex").take();
+}
+
+// Remap renamed note IDs in per-folder settings (pins, last-opened note).
+// Returns true if anything changed.
+fn remap_note_ids_in_settings(settings: &mut Settings, renames: &HashMap<String, String>) -> bool {
+    let mut changed = false;
+
+    if let Some(ref mut pinned) = settings.pinned_note_ids {
+        for pinned_id in pinned.iter_mut() {
+            if let Some(new_id) = renames.get(pinned_id) {
+                *pinned_id = new_id.clone();
+                changed = true;
+            }
+        }
     }
 
-    // Create assets folder
-    let assets = path_buf.join("assets");
-    std::fs::create_dir_all(&assets).map_err(|e| e.to_string())?;
+    if let Some(new_id) = settings
+        .last_opened_note_id
+        .as_ref()
+        .and_then(|id| renames.get(id))
+    {
+        settings.last_opened_note_id = Some(new_id.clone());
+        changed = true;
+    }
 
-    // Create .scratch config folder
-    let scratch_dir = path_buf.join(".scratch");
-    std::fs::create_dir_all(&scratch_dir).map_err(|e| e.to_string())?;
+    changed
+}
 
-    // Verify write access early to avoid later silent failures
+// File modification time in seconds since the epoch (0 if unavailable)
+fn mtime_secs(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// File modification time in milliseconds since the epoch (0 if unavailable).
+// Seconds are too coarse to notice a sync client writing right after a load.
+fn mtime_millis(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Write a file atomically: write a hidden temp file next to the target, then rename it
+/// over the target so readers (and the file watcher) never see a half-written note.
+async fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    fs::write(&tmp_path, contents).await?;
+    if let Err(e) = fs::rename(&tmp_path, path).await {
+        let _ = fs::remove_file(&tmp_path).await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Turn a note write failure into a user-facing message (a full disk is otherwise cryptic)
+fn describe_write_error(e: std::io::Error) -> String {
+    if e.kind() == std::io::ErrorKind::StorageFull {
+        "Disk full: free up space on the drive holding your notes folder and try again".to_string()
+    } else {
+        e.to_string()
+    }
+}
+
+// Verify the notes folder is writable by writing and removing a probe file in .scratch/
+fn check_folder_writable(notes_root: &Path) -> Result<(), String> {
+    let scratch_dir = notes_root.join(".scratch");
+    std::fs::create_dir_all(&scratch_dir)
+        .map_err(|e| format!("Notes folder is not writable: {}", e))?;
     let write_test_path = scratch_dir.join(".write-test");
     std::fs::write(&write_test_path, b"ok")
         .map_err(|e| format!("Notes folder is not writable: {}", e))?;
     let _ = std::fs::remove_file(&write_test_path);
+    Ok(())
+}
 
-    // Load per-folder settings (starts fresh with defaults if none exist)
-    let settings = load_settings(&normalized_path);
+// Get app config file path (in app data directory)
+fn get_app_config_path(app: &AppHandle) -> Result<PathBuf> {
+    let app_data = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&app_data)?;
+    Ok(app_data.join("config.json"))
+}
 
-    // Update app config
-    {
-        let mut app_config = state.app_config.write().expect("app_config write lock");
-        app_config.notes_folder = Some(normalized_path.clone());
+// Get per-folder settings file path (in .scratch/ within notes folder)
+fn get_settings_path(notes_folder: &str) -> PathBuf {
+    let scratch_dir = PathBuf::from(notes_folder).join(".scratch");
+    std::fs::create_dir_all(&scratch_dir).ok();
+    scratch_dir.join("settings.json")
+}
+
+// Get the configured Tantivy writer buffer in bytes, clamped to Tantivy's legal range
+fn index_writer_buffer_bytes(settings: &Settings) -> usize {
+    let mb = settings
+        .index_writer_buffer_mb
+        .map(|mb| mb as usize)
+        .unwrap_or(DEFAULT_INDEX_WRITER_BUFFER_MB)
+        .clamp(MIN_INDEX_WRITER_BUFFER_MB, MAX_INDEX_WRITER_BUFFER_MB);
+    mb * 1_000_000
+}
+
+fn max_folder_depth(settings: &Settings) -> usize {
+    settings
+        .max_folder_depth
+        .unwrap_or(DEFAULT_MAX_FOLDER_DEPTH)
+        .clamp(MIN_MAX_FOLDER_DEPTH, MAX_MAX_FOLDER_DEPTH)
+}
+
+// previewLength / titleFallbackLength of a vault, clamped to their bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TextLengths {
+    preview: usize,
+    title_fallback: usize,
+}
+
+impl Default for TextLengths {
+    fn default() -> Self {
+        text_lengths(&Settings::default())
     }
+}
 
-    // Update settings in memory
-    {
-        let mut current_settings = state.settings.write().expect("settings write lock");
-        *current_settings = settings;
+fn text_lengths(settings: &Settings) -> TextLengths {
+    TextLengths {
+        preview: settings
+            .preview_length
+            .unwrap_or(DEFAULT_PREVIEW_LENGTH)
+            .clamp(MIN_TEXT_LENGTH, MAX_TEXT_LENGTH),
+        title_fallback: settings
+            .title_fallback_length
+            .unwrap_or(DEFAULT_TITLE_FALLBACK_LENGTH)
+            .clamp(MIN_TEXT_LENGTH, MAX_TEXT_LENGTH),
     }
+}
 
-    // Save app config to disk
+// Which notes a scan of the vault sees: maxFolderDepth and followSymlinks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NoteScan {
+    // WalkDir depth, where files directly in the notes folder are depth 1, so notes in
+    // maxFolderDepth levels of subfolders are still found
+    max_depth: usize,
+    follow_symlinks: bool,
+}
+
+fn note_scan(settings: &Settings) -> NoteScan {
+    NoteScan {
+        max_depth: max_folder_depth(settings) + 1,
+        follow_symlinks: settings.follow_symlinks.unwrap_or(false),
+    }
+}
+
+// NoteScan for the open vault
+fn vault_note_scan(state: &AppState) -> NoteScan {
+    note_scan(&state.settings.read().expect("settings read lock"))
+}
+
+// previewLength / titleFallbackLength for the open vault
+fn vault_text_lengths(state: &AppState) -> TextLengths {
+    text_lengths(&state.settings.read().expect("settings read lock"))
+}
+
+// Build the text analyzer for title/content from the search settings. Changing these
+// settings only takes effect after a rebuild (see update_settings).
+fn search_text_analyzer(settings: &Settings) -> TextAnalyzer {
+    let language = settings
+        .search_language
+        .as_deref()
+        .unwrap_or("english")
+        .to_lowercase();
+    let language = match language.as_str() {
+        // CJK text has no word separators, so index 1- and 2-character n-grams instead of
+        // words. The query goes through the same tokenizer, and since n-grams carry no
+        // positions a query matches notes containing all of its n-grams.
+        "cjk" | "chinese" | "japanese" | "korean" => {
+            if let Ok(ngrams) = NgramTokenizer::all_ngrams(1, 2) {
+                let builder = TextAnalyzer::builder(ngrams).filter(LowerCaser).dynamic();
+                return with_hashed_terms(builder, settings).build();
+            }
+            None
+        }
+        "off" | "none" => None,
+        "arabic" => Some(Language::Arabic),
+        "danish" => Some(Language::Danish),
+        "dutch" => Some(Language::Dutch),
+        "finnish" => Some(Language::Finnish),
+        "french" => Some(Language::French),
+        "german" => Some(Language::German),
+        "greek" => Some(Language::Greek),
+        "hungarian" => Some(Language::Hungarian),
+        "italian" => Some(Language::Italian),
+        "norwegian" => Some(Language::Norwegian),
+        "portuguese" => Some(Language::Portuguese),
+        "romanian" => Some(Language::Romanian),
+        "russian" => Some(Language::Russian),
+        "spanish" => Some(Language::Spanish),
+        "swedish" => Some(Language::Swedish),
+        "tamil" => Some(Language::Tamil),
+        "turkish" => Some(Language::Turkish),
+        _ => Some(Language::English),
+    };
+
+    let builder = TextAnalyzer::builder(SimpleTokenizer::default())
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .dynamic();
+    let builder = match (language, settings.search_stop_words.unwrap_or(false)) {
+        (Some(language), true) => match StopWordFilter::new(language) {
+            Some(filter) => builder.filter_dynamic(filter),
+            None => builder,
+        },
+        _ => builder,
+    };
+    let builder = match language {
+        Some(language) => builder.filter_dynamic(Stemmer::new(language)),
+        None => builder,
+    };
+    with_hashed_terms(builder, settings).build()
+}
+
+// With encryptIndexContent, hash terms as the last step so indexing and queries agree
+fn with_hashed_terms(builder: TextAnalyzerBuilder, settings: &Settings) -> TextAnalyzerBuilder {
+    if settings.encrypt_index_content.unwrap_or(false) {
+        let salt = settings.index_salt.as_deref().unwrap_or_default();
+        builder.filter_dynamic(hashed_terms::HashedTerms::new(salt.as_bytes()))
+    } else {
+        builder
+    }
+}
+
+// Folder whose note files supply titles and previews when the index doesn't store them
+fn index_source_root(settings: &Settings, notes_folder: &str) -> Option<PathBuf> {
+    settings
+        .encrypt_index_content
+        .unwrap_or(false)
+        .then(|| PathBuf::from(notes_folder))
+}
+
+// Hex string of `len` random bytes from the OS
+fn random_hex(len: usize) -> Result<String, String> {
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Random hex salt for encryptIndexContent
+fn generate_index_salt() -> Result<String, String> {
+    random_hex(16)
+}
+
+// Folder holding the search index of every vault
+fn get_search_index_root(app: &AppHandle) -> Result<PathBuf> {
+    // Before AppState is managed (during setup) the config is read from disk
+    let location = match app.try_state::<AppState>() {
+        Some(state) => state
+            .app_config
+            .read()
+            .expect("app_config read lock")
+            .search_index_location
+            .clone(),
+        None => load_app_config(app).search_index_location,
+    };
+    search_index_root_at(app, location)
+}
+
+// The search index root for a searchIndexLocation value (None = app data)
+fn search_index_root_at(app: &AppHandle, location: Option<String>) -> Result<PathBuf> {
+    let parent = match location {
+        Some(location) => PathBuf::from(location),
+        None => app.path().app_data_dir()?,
+    };
+    std::fs::create_dir_all(&parent)?;
+    Ok(parent.join("search_index"))
+}
+
+// Each vault keeps its own index under the root, so search_all_vaults can read the
+// indexes of vaults that aren't open
+fn vault_search_index_path(root: &Path, notes_folder: &str) -> PathBuf {
+    root.join(&content_hash(notes_folder.as_bytes())[..16])
+}
+
+// Get the search index path for a notes folder
+fn get_search_index_path(app: &AppHandle, notes_folder: &str) -> Result<PathBuf> {
+    let root = get_search_index_root(app)?;
+    Ok(vault_search_index_path(&root, notes_folder))
+}
+
+// Move a directory, copying it when a rename isn't possible (e.g. across drives)
+fn move_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_dir(from, to)?;
+    std::fs::remove_dir_all(from)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Keep the search indexes under `path` (absolute) instead of app data, or back in app
+/// data if `path` is None. The open vault's index is rebuilt at the new location and the
+/// other vaults' indexes are moved there, so search_all_vaults keeps finding them. The
+/// old folder is deleted once everything has moved. A location inside the current
+/// index folder (or containing it) is rejected.
+#[tauri::command]
+async fn set_search_index_location(
+    app: AppHandle,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let location = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(ref location) = location {
+        let dir = Path::new(location);
+        if !dir.is_absolute() {
+            return Err("Search index location must be an absolute path".to_string());
+        }
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Search index location is not writable: {}", e))?;
+        let write_test_path = dir.join(".scratch-write-test");
+        std::fs::write(&write_test_path, b"ok")
+            .map_err(|e| format!("Search index location is not writable: {}", e))?;
+        let _ = std::fs::remove_file(&write_test_path);
+    }
+
+    let old_index_root = get_search_index_root(&app).map_err(|e| e.to_string())?;
+    let new_index_root = search_index_root_at(&app, location.clone()).map_err(|e| e.to_string())?;
+    // Both parents exist now, so symlinks and `..` can be resolved before comparing
+    let canonical_root = |root: &Path| {
+        root.parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .map(|parent| parent.join("search_index"))
+            .unwrap_or_else(|| root.to_path_buf())
+    };
+    let (old_canonical, new_canonical) = (
+        canonical_root(&old_index_root),
+        canonical_root(&new_index_root),
+    );
+    if old_canonical != new_canonical
+        && (new_canonical.starts_with(&old_canonical) || old_canonical.starts_with(&new_canonical))
     {
-        let app_config = state.app_config.read().expect("app_config read lock");
+        return Err(
+            "The search index location can't be inside the current index folder or contain it"
+                .to_string(),
+        );
+    }
+
+    let previous = {
+        let mut app_config = state.app_config.write().expect("app_config write lock");
+        let previous = std::mem::replace(&mut app_config.search_index_location, location);
         save_app_config(&app, &app_config).map_err(|e| e.to_string())?;
+        previous
+    };
+    if new_canonical == old_canonical {
+        return Ok(());
+    }
+
+    let open_vault = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone();
+    if open_vault.is_some() {
+        if let Err(e) = rebuild_search_index(app.clone(), state.clone()).await {
+            // Go back to the old location so search keeps working
+            {
+                let mut app_config = state.app_config.write().expect("app_config write lock");
+                app_config.search_index_location = previous;
+                let _ = save_app_config(&app, &app_config);
+            }
+            let _ = rebuild_search_index(app, state).await;
+            return Err(format!("Failed to build the search index at the new location: {}", e));
+        }
+    }
+
+    // Move the other vaults' indexes. The open vault's was rebuilt at the new location
+    // (which dropped the old writer), so its old copy is left to be deleted.
+    let open_index = open_vault.map(|folder| vault_search_index_path(&old_index_root, &folder));
+    let mut migrated = true;
+    if let Ok(entries) = std::fs::read_dir(&old_index_root) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_dir() || Some(&path) == open_index.as_ref() {
+                continue;
+            }
+            let target = new_index_root.join(entry.file_name());
+            if target.exists() {
+                continue;
+            }
+            if let Err(e) = move_dir(&path, &target) {
+                eprintln!(
+                    "Failed to move search index {:?} to {:?}: {}",
+                    path, target, e
+                );
+                migrated = false;
+            }
+        }
+    }
+
+    // Anything left behind is kept rather than lost; it's rebuilt when its vault opens
+    if migrated && old_index_root.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&old_index_root) {
+            eprintln!("Failed to remove old search indexes {:?}: {}", old_index_root, e);
+        }
+    }
+    Ok(())
+}
+
+/// The app data folder, config file and search index locations, for diagnostics
+#[tauri::command]
+fn get_app_paths(app: AppHandle, state: State<AppState>) -> Result<AppPaths, String> {
+    let app_data = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let config_file = get_app_config_path(&app).map_err(|e| e.to_string())?;
+    let index_root = get_search_index_root(&app).map_err(|e| e.to_string())?;
+    let folder = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone();
+    let search_index = match folder {
+        Some(folder) => vault_search_index_path(&index_root, &folder),
+        None => index_root,
+    };
+    Ok(AppPaths {
+        app_data_dir: AppPath::new(&app_data),
+        config_file: AppPath::new(&config_file),
+        search_index: AppPath::new(&search_index),
+    })
+}
+
+// Load app config from disk (notes folder path)
+fn load_app_config(app: &AppHandle) -> AppConfig {
+    let path = match get_app_config_path(app) {
+        Ok(p) => p,
+        Err(_) => return AppConfig::default(),
+    };
+
+    if path.exists() {
+        std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    } else {
+        AppConfig::default()
+    }
+}
+
+// Save app config to disk
+fn save_app_config(app: &AppHandle, config: &AppConfig) -> Result<()> {
+    let path = get_app_config_path(app)?;
+    let content = serde_json::to_string_pretty(config)?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+// Load per-folder settings from disk
+fn load_settings(notes_folder: &str) -> Settings {
+    let path = get_settings_path(notes_folder);
+
+    if path.exists() {
+        std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    } else {
+        Settings::default()
+    }
+}
+
+// Save per-folder settings to disk
+fn save_settings(notes_folder: &str, settings: &Settings) -> Result<()> {
+    let path = get_settings_path(notes_folder);
+    let content = serde_json::to_string_pretty(settings)?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+// Most recent search queries remembered per vault
+const MAX_RECENT_SEARCHES: usize = 50;
+
+// Get per-folder recent searches file path (in .scratch/ within notes folder)
+fn get_recent_searches_path(notes_folder: &str) -> PathBuf {
+    let scratch_dir = PathBuf::from(notes_folder).join(".scratch");
+    std::fs::create_dir_all(&scratch_dir).ok();
+    scratch_dir.join("recent-searches.json")
+}
+
+// Load recent search queries, most recent first
+fn load_recent_searches(notes_folder: &str) -> Vec<String> {
+    std::fs::read_to_string(get_recent_searches_path(notes_folder))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// Move a query to the front of the recent searches (case-insensitive de-dup) and save
+fn record_recent_search(notes_folder: &str, query: &str) -> Result<()> {
+    let mut recent = load_recent_searches(notes_folder);
+    let lower = query.to_lowercase();
+    recent.retain(|q| q.to_lowercase() != lower);
+    recent.insert(0, query.to_string());
+    recent.truncate(MAX_RECENT_SEARCHES);
+    let content = serde_json::to_string_pretty(&recent)?;
+    std::fs::write(get_recent_searches_path(notes_folder), content)?;
+    Ok(())
+}
+
+// AI runs remembered per vault, and the longest summary kept for each
+const MAX_AI_HISTORY: usize = 200;
+const AI_HISTORY_SUMMARY_CHARS: usize = 200;
+
+// Get per-folder AI history file path (in .scratch/ within notes folder)
+fn get_ai_history_path(notes_folder: &str) -> PathBuf {
+    let scratch_dir = PathBuf::from(notes_folder).join(".scratch");
+    std::fs::create_dir_all(&scratch_dir).ok();
+    scratch_dir.join("ai-history.json")
+}
+
+// Load AI history, most recent first
+fn load_ai_history(notes_folder: &str) -> Vec<AiHistoryEntry> {
+    std::fs::read_to_string(get_ai_history_path(notes_folder))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// Add an entry to the front of the AI history and save, dropping the oldest past the cap
+fn record_ai_history(notes_folder: &str, entry: AiHistoryEntry) -> Result<()> {
+    let mut history = load_ai_history(notes_folder);
+    history.insert(0, entry);
+    history.truncate(MAX_AI_HISTORY);
+    let content = serde_json::to_string_pretty(&history)?;
+    std::fs::write(get_ai_history_path(notes_folder), content)?;
+    Ok(())
+}
+
+// The app instance that has a vault open, recorded in .scratch/lock so a second
+// instance (another machine on a synced folder, a dev build) doesn't write the same
+// index and settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultLock {
+    pub pid: u32,
+    pub hostname: String,
+    pub acquired_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultLockStatus {
+    pub held_by_other: bool,
+    pub holder: Option<VaultLock>,
+}
+
+fn get_vault_lock_path(notes_folder: &str) -> PathBuf {
+    PathBuf::from(notes_folder).join(".scratch").join("lock")
+}
+
+// This machine's name, looked up once: HOSTNAME usually isn't exported to GUI apps, and
+// the lock is checked on every write
+fn host_name() -> String {
+    static HOST_NAME: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    HOST_NAME
+        .get_or_init(|| {
+            std::env::var("COMPUTERNAME")
+                .or_else(|_| std::env::var("HOSTNAME"))
+                .ok()
+                .or_else(|| {
+                    std::process::Command::new("hostname")
+                        .output()
+                        .ok()
+                        .filter(|o| o.status.success())
+                        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                })
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "unknown".to_string())
+        })
+        .clone()
+}
+
+// Whether a process on this machine is still running. Assumes it is when unsure.
+fn process_alive(pid: u32) -> bool {
+    // Signal 0 only checks the process exists. EPERM means it does but belongs to
+    // another user, so only ESRCH counts as gone.
+    #[cfg(unix)]
+    let output: std::io::Result<bool> = match libc::pid_t::try_from(pid) {
+        Ok(pid) if unsafe { libc::kill(pid, 0) } == 0 => Ok(true),
+        Ok(_) => Ok(std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)),
+        Err(_) => Ok(true),
+    };
+    #[cfg(windows)]
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains(&pid.to_string()));
+    #[cfg(not(any(unix, windows)))]
+    let output: std::io::Result<bool> = Ok(true);
+    output.unwrap_or(true)
+}
+
+fn read_vault_lock(notes_folder: &str) -> Option<VaultLock> {
+    std::fs::read_to_string(get_vault_lock_path(notes_folder))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+// A lock from another machine can't be checked, so it counts as held; one left by a
+// process on this machine that has exited is stale
+fn lock_held_by_other(lock: &VaultLock) -> bool {
+    if lock.hostname != host_name() {
+        return true;
+    }
+    lock.pid != std::process::id() && process_alive(lock.pid)
+}
+
+fn held_by_other_error(holder: &VaultLock) -> String {
+    format!(
+        "This vault is open in another instance of the app ({}, process {}). Close it there or take over the vault.",
+        holder.hostname, holder.pid
+    )
+}
+
+// How long a lock file that can't be parsed is treated as held: on filesystems without
+// hard links it is written in place, so another instance may be mid-write
+const VAULT_LOCK_WRITE_GRACE: Duration = Duration::from_secs(5);
+
+// Publish `content` as the lock file only if none exists. It's written to a temp file
+// first and hard-linked into place, so other instances never see a partial lock.
+fn create_vault_lock_file(path: &Path, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temp_path, content)?;
+    let linked = std::fs::hard_link(&temp_path, path);
+    let _ = std::fs::remove_file(&temp_path);
+    match linked {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+            // No hard links here (e.g. FAT drives): fall back to create_new, covered by
+            // VAULT_LOCK_WRITE_GRACE
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(content.as_bytes()))
+        }
+        result => result,
+    }
+}
+
+// Whether the lock file was written within VAULT_LOCK_WRITE_GRACE
+fn vault_lock_is_fresh(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .is_some_and(|age| age < VAULT_LOCK_WRITE_GRACE)
+}
+
+// Take the vault's lock for this instance, failing if another instance holds it unless
+// `force` is set. The lock file is published without clobbering so two instances
+// starting at once can't both believe they won; an existing file is only replaced when
+// it's stale.
+fn acquire_vault_lock(notes_folder: &str, force: bool) -> Result<(), String> {
+    let lock = VaultLock {
+        pid: std::process::id(),
+        hostname: host_name(),
+        acquired_at: chrono::Utc::now().timestamp(),
+    };
+    let content = serde_json::to_string_pretty(&lock).map_err(|e| e.to_string())?;
+    let path = get_vault_lock_path(notes_folder);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let create = || create_vault_lock_file(&path, &content);
+    match create() {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e.to_string()),
+        Err(_) => {}
+    }
+
+    if force {
+        return std::fs::write(&path, &content).map_err(|e| e.to_string());
+    }
+    match read_vault_lock(notes_folder) {
+        Some(holder) if holder.pid == lock.pid && holder.hostname == lock.hostname => {
+            return Ok(());
+        }
+        Some(holder) if lock_held_by_other(&holder) => return Err(held_by_other_error(&holder)),
+        None if vault_lock_is_fresh(&path) => {
+            return Err("This vault is being opened by another instance of the app".to_string());
+        }
+        // Stale or unreadable: clear it and race for it once more
+        _ => {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+    match create() {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            Err(read_vault_lock(notes_folder)
+                .map(|holder| held_by_other_error(&holder))
+                .unwrap_or_else(|| {
+                    "This vault is being opened by another instance of the app".to_string()
+                }))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Remove the vault's lock if this instance holds it
+fn release_vault_lock(notes_folder: &str) {
+    let ours = read_vault_lock(notes_folder)
+        .is_some_and(|lock| lock.pid == std::process::id() && lock.hostname == host_name());
+    if ours {
+        let _ = std::fs::remove_file(get_vault_lock_path(notes_folder));
+    }
+}
+
+// Clean up old entries from debounce map (entries older than 5 seconds)
+fn cleanup_debounce_map(map: &Mutex<HashMap<PathBuf, Instant>>) {
+    let mut map = map.lock().expect("debounce map mutex");
+    let now = Instant::now();
+    map.retain(|_, last| now.duration_since(*last) < Duration::from_secs(5));
+}
+
+// Normalize notes folder path from plain paths and legacy file:// URIs.
+fn normalize_notes_folder_path(path: &str) -> Result<PathBuf, String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("Notes folder path is empty".to_string());
+    }
+
+    if trimmed.starts_with("file://") {
+        let parsed = url::Url::parse(trimmed)
+            .map_err(|e| format!("Invalid file URL for notes folder: {}", e))?;
+        return parsed
+            .to_file_path()
+            .map_err(|_| "Invalid file URL for notes folder".to_string());
+    }
+
+    Ok(PathBuf::from(trimmed))
+}
+
+// TAURI COMMANDS
+
+#[tauri::command]
+fn get_notes_folder(state: State<AppState>) -> Option<String> {
+    state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone()
+}
+
+// The presentation and template settings a new vault copies from an existing one.
+// Vault-specific state (pinned notes, last opened note, index salt, ...) isn't copied.
+fn inherited_settings(source: &Settings) -> Settings {
+    Settings {
+        theme: source.theme.clone(),
+        editor_font: source.editor_font.clone(),
+        text_direction: source.text_direction.clone(),
+        editor_width: source.editor_width.clone(),
+        default_note_name: source.default_note_name.clone(),
+        commit_message_template: source.commit_message_template.clone(),
+        preview_length: source.preview_length,
+        title_fallback_length: source.title_fallback_length,
+        capture_note_id: source.capture_note_id.clone(),
+        ai_system_prompt: source.ai_system_prompt.clone(),
+        ..Settings::default()
+    }
+}
+
+// A brand-new vault can start from another vault's look instead of the defaults
+fn inherit_vault_settings(notes_folder: &str, source: Option<String>) -> Result<(), String> {
+    let Some(source) = source.filter(|s| !s.trim().is_empty()) else {
+        return Ok(());
+    };
+    if get_settings_path(notes_folder).exists() {
+        return Ok(());
+    }
+    let source = normalize_notes_folder_path(&source)?;
+    if !get_settings_path(&source.to_string_lossy()).exists() {
+        return Err(format!("No settings to copy in {}", source.display()));
+    }
+    let inherited = inherited_settings(&load_settings(&source.to_string_lossy()));
+    save_settings(notes_folder, &inherited).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_notes_folder(
+    app: AppHandle,
+    path: String,
+    inherit_settings_from: Option<String>,
+    force_takeover: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let path_buf = normalize_notes_folder_path(&path)?;
+    let normalized_path = path_buf.to_string_lossy().into_owned();
+
+    // Verify it's a valid directory
+    if !path_buf.exists() {
+        std::fs::create_dir_all(&path_buf).map_err(|e| e.to_string())?;
+    }
+
+    // Create assets folder
+    let assets = path_buf.join("assets");
+    std::fs::create_dir_all(&assets).map_err(|e| e.to_string())?;
+
+    // Create .scratch config folder
+    let scratch_dir = path_buf.join(".scratch");
+    std::fs::create_dir_all(&scratch_dir).map_err(|e| e.to_string())?;
+
+    // Verify write access early to avoid later silent failures
+    check_folder_writable(&path_buf)?;
+
+    // Only one app instance may write a vault's index and settings at a time. The
+    // previous vault's lock is kept until the switch below has happened, and the new
+    // one is given back if the switch fails before that.
+    acquire_vault_lock(&normalized_path, force_takeover.unwrap_or(false))?;
+    let previous_folder = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone();
+    if let Err(e) = inherit_vault_settings(&normalized_path, inherit_settings_from) {
+        if previous_folder.as_deref() != Some(normalized_path.as_str()) {
+            release_vault_lock(&normalized_path);
+        }
+        return Err(e);
+    }
+
+    // Load per-folder settings (starts fresh with defaults if none exist)
+    let settings = load_settings(&normalized_path);
+    let writer_buffer_bytes = index_writer_buffer_bytes(&settings);
+    let analyzer = search_text_analyzer(&settings);
+    let scan = note_scan(&settings);
+    let title_fallback_length = text_lengths(&settings).title_fallback;
+    let source_root = index_source_root(&settings, &normalized_path);
+
+    // Update app config
+    {
+        let mut app_config = state.app_config.write().expect("app_config write lock");
+        app_config.notes_folder = Some(normalized_path.clone());
+        let recent = app_config.recent_vaults.get_or_insert_with(Vec::new);
+        recent.retain(|p| *p != normalized_path);
+        recent.insert(0, normalized_path.clone());
+        recent.truncate(MAX_RECENT_VAULTS);
+    }
+    if let Some(previous) = previous_folder.filter(|p| *p != normalized_path) {
+        release_vault_lock(&previous);
+    }
+
+    // The undo buffer and served-content hashes refer to the previous vault
+    clear_undo(&state);
+    state.served_hashes.lock().expect("served hashes mutex").clear();
+
+    // Update settings in memory; cached titles/previews are cut to the old vault's lengths
+    if vault_text_lengths(&state) != text_lengths(&settings) {
+        state.notes_cache.write().expect("cache write lock").clear();
+    }
+    {
+        let mut current_settings = state.settings.write().expect("settings write lock");
+        *current_settings = settings;
+    }
+
+    // Save app config to disk
+    {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        save_app_config(&app, &app_config).map_err(|e| e.to_string())?;
+    }
+
+    // Initialize search index (drop the old writer first so its directory lock is released).
+    // The rebuild runs off the main thread so index-progress events reach the UI as they happen.
+    if let Ok(index_path) = get_search_index_path(&app, &normalized_path) {
+        state.search_index.lock().expect("search index mutex").take();
+        let app_clone = app.clone();
+        let rebuilt = tokio::task::spawn_blocking(move || {
+            let search_index =
+                SearchIndex::new(&index_path, writer_buffer_bytes, analyzer, source_root).ok()?;
+            let _ = search_index.rebuild_index(
+                &path_buf,
+                scan,
+                title_fallback_length,
+                Some(&app_clone),
+            );
+            Some(search_index)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        if let Some(search_index) = rebuilt {
+            let mut index = state.search_index.lock().expect("search index mutex");
+            *index = Some(search_index);
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_recent_vaults(state: State<AppState>) -> Result<Vec<String>, String> {
+    let app_config = state.app_config.read().expect("app_config read lock");
+
+    // Hide vaults that are missing right now (deleted, moved, or on an unmounted
+    // drive) but keep them in the config in case they come back
+    Ok(app_config
+        .recent_vaults
+        .iter()
+        .flatten()
+        .filter(|path| Path::new(path).is_dir())
+        .cloned()
+        .collect())
+}
+
+#[tauri::command]
+async fn open_recent_vault(
+    app: AppHandle,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    // Unlike set_notes_folder, don't recreate a vault that has disappeared
+    if !Path::new(&path).is_dir() {
+        return Err(format!("Vault not found: {}", path));
+    }
+    set_notes_folder(app, path, None, None, state).await
+}
+
+// Secondary order of the note list, after pinned notes come first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NoteSort {
+    Modified,
+    Title,
+}
+
+// list_notes / note_neighbors `sort`: "modified" (newest first, the default) or "title"
+fn parse_note_sort(sort: Option<&str>) -> Result<NoteSort, String> {
+    match sort {
+        None | Some("modified") => Ok(NoteSort::Modified),
+        Some("title") => Ok(NoteSort::Title),
+        Some(other) => Err(format!("Unknown sort \"{}\" (expected modified or title)", other)),
+    }
+}
+
+fn pinned_note_ids(state: &AppState) -> HashSet<String> {
+    let settings = state.settings.read().expect("settings read lock");
+    settings
+        .pinned_note_ids
+        .as_ref()
+        .map(|ids| ids.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+// Sort: pinned notes first, then unpinned notes, each by `sort`
+fn sort_note_list(notes: &mut [NoteMetadata], pinned_ids: &HashSet<String>, sort: NoteSort) {
+    notes.sort_by(|a, b| {
+        let a_pinned = pinned_ids.contains(&a.id);
+        let b_pinned = pinned_ids.contains(&b.id);
+
+        match (a_pinned, b_pinned) {
+            (true, false) => std::cmp::Ordering::Less,    // a pinned, b not -> a first
+            (false, true) => std::cmp::Ordering::Greater, // b pinned, a not -> b first
+            _ => match sort {
+                // Newest first; ties by ID so the order is stable across calls
+                NoteSort::Modified => b.modified.cmp(&a.modified).then_with(|| a.id.cmp(&b.id)),
+                NoteSort::Title => a
+                    .title
+                    .to_lowercase()
+                    .cmp(&b.title.to_lowercase())
+                    .then_with(|| a.id.cmp(&b.id)),
+            },
+        }
+    });
+}
+
+#[tauri::command]
+async fn list_notes(
+    include_content: Option<bool>,
+    sort: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteMetadata>, String> {
+    let sort = parse_note_sort(sort.as_deref())?;
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let path = PathBuf::from(&folder);
+    if !path.is_dir() {
+        return Err(VAULT_UNAVAILABLE.to_string());
+    }
+
+    let include_content = include_content.unwrap_or(false);
+
+    // Title/preview from the last listing, reused for files whose mtime hasn't changed
+    let previous: HashMap<String, NoteMetadata> = state
+        .notes_cache
+        .read()
+        .expect("cache read lock")
+        .clone();
+
+    let path_clone = path.clone();
+    let scan = vault_note_scan(&state);
+    let lengths = vault_text_lengths(&state);
+    let mut notes = tokio::task::spawn_blocking(move || {
+        let mut results: Vec<NoteMetadata> = Vec::new();
+        for (id, entry) in walk_notes(&path_clone, scan) {
+            let metadata = entry.metadata().ok();
+            let modified = metadata.as_ref().map(mtime_secs).unwrap_or(0);
+            let modified_ms = metadata.as_ref().map(mtime_millis).unwrap_or(0);
+            let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+
+            if !include_content && modified_ms != 0 {
+                if let Some(cached) = previous
+                    .get(&id)
+                    .filter(|c| c.modified_ms == modified_ms && c.size == size)
+                {
+                    results.push(cached.clone());
+                    continue;
+                }
+            }
+
+            if let Ok(content) = read_note_file(entry.path()) {
+                let title = extract_title(&content, lengths.title_fallback);
+                let preview = generate_preview(&content, lengths.preview);
+                let tags = note_tags(&content);
+                results.push(NoteMetadata {
+                    id,
+                    title,
+                    preview,
+                    modified,
+                    content: include_content.then_some(content),
+                    modified_ms,
+                    size,
+                    tags,
+                });
+            }
+        }
+        results
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Forget the last-opened note if it no longer exists. Only the instance holding the
+    // vault lock writes settings.json.
+    {
+        let holds_lock = ensure_vault_lock(&state).is_ok();
+        let mut settings = state.settings.write().expect("settings write lock");
+        let is_stale = settings
+            .last_opened_note_id
+            .as_ref()
+            .is_some_and(|last_id| !notes.iter().any(|note| &note.id == last_id));
+        if is_stale {
+            settings.last_opened_note_id = None;
+            if holds_lock {
+                let _ = save_settings(&folder, &settings);
+            }
+        }
+    }
+
+    sort_note_list(&mut notes, &pinned_note_ids(&state), sort);
+
+    // Update cache efficiently
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        cache.clear();
+        for note in &notes {
+            // Content isn't kept in the cache
+            cache.insert(
+                note.id.clone(),
+                NoteMetadata {
+                    id: note.id.clone(),
+                    title: note.title.clone(),
+                    preview: note.preview.clone(),
+                    modified: note.modified,
+                    content: None,
+                    modified_ms: note.modified_ms,
+                    size: note.size,
+                    tags: note.tags.clone(),
+                },
+            );
+        }
+    }
+
+    Ok(notes)
+}
+
+// note_neighbors result: the notes before and after one in the sorted note list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteNeighbors {
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+/// The notes before and after `id` in the note list as list_notes last returned it,
+/// sorted the same way (pass the same `sort`), for prev/next navigation
+#[tauri::command]
+fn note_neighbors(
+    id: String,
+    sort: Option<String>,
+    state: State<AppState>,
+) -> Result<NoteNeighbors, String> {
+    let sort = parse_note_sort(sort.as_deref())?;
+    let mut notes: Vec<NoteMetadata> = state
+        .notes_cache
+        .read()
+        .expect("cache read lock")
+        .values()
+        .cloned()
+        .collect();
+    sort_note_list(&mut notes, &pinned_note_ids(&state), sort);
+
+    let index = notes
+        .iter()
+        .position(|note| note.id == id)
+        .ok_or("Note not found")?;
+    Ok(NoteNeighbors {
+        previous: index.checked_sub(1).map(|i| notes[i].id.clone()),
+        next: notes.get(index + 1).map(|note| note.id.clone()),
+    })
+}
+
+/// Autocomplete candidates for `[[` links (kind "link") or `#` tags (kind "tag"), from
+/// the note list cache so it's cheap enough to call per keystroke. Prefix matches rank
+/// above substring matches; ties go to recently modified notes or frequently used tags.
+#[tauri::command]
+fn suggest(prefix: String, kind: String, state: State<AppState>) -> Result<Vec<Suggestion>, String> {
+    let prefix = prefix.trim().to_lowercase();
+    let rank = |candidates: &[&str]| -> Option<u8> {
+        if candidates.iter().any(|c| c.to_lowercase().starts_with(&prefix)) {
+            Some(0)
+        } else if candidates.iter().any(|c| c.to_lowercase().contains(&prefix)) {
+            Some(1)
+        } else {
+            None
+        }
+    };
+
+    let cache = state.notes_cache.read().expect("cache read lock");
+    let mut ranked: Vec<(u8, i64, Suggestion)> = match kind.as_str() {
+        "link" => cache
+            .values()
+            .filter_map(|note| {
+                let leaf = note.id.rsplit('/').next().unwrap_or(&note.id);
+                let rank = rank(&[note.title.as_str(), leaf, note.id.as_str()])?;
+                Some((
+                    rank,
+                    note.modified,
+                    Suggestion {
+                        value: note.id.clone(),
+                        label: note.title.clone(),
+                    },
+                ))
+            })
+            .collect(),
+        "tag" => {
+            let mut usage: HashMap<&str, i64> = HashMap::new();
+            for tag in cache.values().flat_map(|note| &note.tags) {
+                *usage.entry(tag.as_str()).or_default() += 1;
+            }
+            let prefix_tag = prefix.trim_start_matches('#');
+            usage
+                .into_iter()
+                .filter_map(|(tag, count)| {
+                    let rank = if tag.starts_with(prefix_tag) {
+                        0
+                    } else if tag.contains(prefix_tag) {
+                        1
+                    } else {
+                        return None;
+                    };
+                    Some((
+                        rank,
+                        count,
+                        Suggestion {
+                            value: tag.to_string(),
+                            label: tag.to_string(),
+                        },
+                    ))
+                })
+                .collect()
+        }
+        _ => return Err(format!("Unknown suggestion kind \"{}\" (expected link or tag)", kind)),
+    };
+
+    ranked.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.cmp(&a.1))
+            .then_with(|| a.2.label.cmp(&b.2.label))
+    });
+    Ok(ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, suggestion)| suggestion)
+        .collect())
+}
+
+#[tauri::command]
+async fn note_hash(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || hash_note_file(&file_path).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// A note's on-disk modified time (Unix seconds), without reading its content
+#[tauri::command]
+async fn note_modified(id: String, state: State<'_, AppState>) -> Result<i64, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    let metadata = fs::metadata(&file_path)
+        .await
+        .map_err(|_| "Note not found".to_string())?;
+
+    Ok(mtime_secs(&metadata))
+}
+
+#[tauri::command]
+async fn vault_hashes(state: State<'_, AppState>) -> Result<HashMap<String, String>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let scan = vault_note_scan(&state);
+    tokio::task::spawn_blocking(move || {
+        use rayon::prelude::*;
+
+        let folder_path = PathBuf::from(&folder);
+        let entries: Vec<(String, walkdir::DirEntry)> = walk_notes(&folder_path, scan).collect();
+        entries
+            .into_par_iter()
+            .filter_map(|(id, entry)| hash_note_file(entry.path()).ok().map(|hash| (id, hash)))
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vault_stats(state: State<'_, AppState>) -> Result<VaultStats, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    // Reuse a recent result so repeated dashboard refreshes don't rescan
+    {
+        let cache = state.vault_stats_cache.lock().expect("vault stats cache mutex");
+        if let Some((ref cached_folder, computed_at, ref stats)) = *cache {
+            if *cached_folder == folder && computed_at.elapsed() < VAULT_STATS_TTL {
+                return Ok(stats.clone());
+            }
+        }
+    }
+
+    let path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    let stats = tokio::task::spawn_blocking(move || {
+        let mut stats = VaultStats::default();
+
+        for (_, entry) in walk_notes(&path, scan) {
+            if let Ok(content) = read_note_file(entry.path()) {
+                stats.note_count += 1;
+                stats.word_count += count_words(&content);
+                stats.total_bytes += content.len() as u64;
+            }
+        }
+
+        for entry in walkdir::WalkDir::new(path.join("assets")).into_iter().flatten() {
+            if entry.file_type().is_file() {
+                stats.asset_count += 1;
+                stats.total_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+
+        stats
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut cache = state.vault_stats_cache.lock().expect("vault stats cache mutex");
+    *cache = Some((folder, Instant::now(), stats.clone()));
+
+    Ok(stats)
+}
+
+#[tauri::command]
+async fn list_tasks(
+    only_open: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<TaskItem>, String> {
+    let only_open = only_open.unwrap_or(false);
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    tokio::task::spawn_blocking(move || {
+        let mut notes = load_vault_notes(&path, scan);
+        notes.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut tasks = Vec::new();
+        for (id, content) in notes {
+            for (index, checked, text) in task_lines(&content) {
+                if only_open && checked {
+                    continue;
+                }
+                tasks.push(TaskItem {
+                    note_id: id.clone(),
+                    line: index + 1,
+                    text: text.to_string(),
+                    checked,
+                });
+            }
+        }
+        tasks
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Count case-insensitive occurrences of `term` across all notes
+#[tauri::command]
+async fn term_frequency(term: String, state: State<'_, AppState>) -> Result<TermFrequency, String> {
+    let needle = term.trim().to_lowercase();
+    if needle.is_empty() {
+        return Err("Search term is empty".to_string());
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    tokio::task::spawn_blocking(move || {
+        let mut frequency = TermFrequency {
+            occurrences: 0,
+            note_count: 0,
+        };
+        for (_, content) in load_vault_notes(&path, scan) {
+            let hits = content.to_lowercase().matches(needle.as_str()).count();
+            if hits > 0 {
+                frequency.occurrences += hits;
+                frequency.note_count += 1;
+            }
+        }
+        frequency
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn find_duplicate_titles(
+    state: State<'_, AppState>,
+) -> Result<Vec<DuplicateTitleGroup>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    tokio::task::spawn_blocking(move || {
+        let mut by_title: HashMap<String, Vec<String>> = HashMap::new();
+        for (id, entry) in walk_notes(&path, scan) {
+            if let Ok(content) = read_note_file(entry.path()) {
+                by_title
+                    .entry(extract_title(&content, title_fallback_length))
+                    .or_default()
+                    .push(id);
+            }
+        }
+
+        let mut groups: Vec<DuplicateTitleGroup> = by_title
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|(title, mut ids)| {
+                ids.sort();
+                DuplicateTitleGroup { title, ids }
+            })
+            .collect();
+        groups.sort_by(|a, b| a.title.cmp(&b.title));
+        groups
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// One `<<<<<<<` ... `=======` ... `>>>>>>>` block left in a note by a conflicted merge.
+// Lines are 1-based and cover the markers; a diff3 base section is left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictHunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub ours: String,
+    pub theirs: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictedNote {
+    pub id: String,
+    pub hunks: Vec<ConflictHunk>,
+}
+
+/// Merge conflict blocks in `content`. Only complete blocks count: a stray `=======`
+/// (a setext heading underline) or an unclosed `<<<<<<<` is not a conflict.
+fn conflict_hunks(content: &str) -> Vec<ConflictHunk> {
+    enum Side {
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut hunks = Vec::new();
+    let mut open: Option<(usize, Side, Vec<&str>, Vec<&str>)> = None;
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let Some((start, side, ours, theirs)) = open.as_mut() else {
+            if line.starts_with("<<<<<<<") {
+                open = Some((line_number, Side::Ours, Vec::new(), Vec::new()));
+            }
+            continue;
+        };
+
+        match side {
+            Side::Ours | Side::Base if line.trim_end() == "=======" => *side = Side::Theirs,
+            Side::Ours if line.starts_with("|||||||") => *side = Side::Base,
+            Side::Ours if line.starts_with("<<<<<<<") => {
+                // The earlier block was never closed; start over from this one
+                *start = line_number;
+                ours.clear();
+            }
+            Side::Ours => ours.push(line),
+            Side::Base => {}
+            Side::Theirs if line.starts_with(">>>>>>>") => {
+                hunks.push(ConflictHunk {
+                    start_line: *start,
+                    end_line: line_number,
+                    ours: ours.join("\n"),
+                    theirs: theirs.join("\n"),
+                });
+                open = None;
+            }
+            Side::Theirs => theirs.push(line),
+        }
+    }
+    hunks
+}
+
+/// `content` with every conflict block replaced by the chosen side ("ours", "theirs",
+/// or "both", which keeps ours followed by theirs)
+fn resolve_conflict_hunks(content: &str, choice: &str) -> Result<String, String> {
+    let hunks = conflict_hunks(content);
+    if hunks.is_empty() {
+        return Err("Note has no merge conflicts".to_string());
+    }
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let in_hunk = |line_number: usize| {
+        hunks
+            .iter()
+            .any(|h| (h.start_line..=h.end_line).contains(&line_number))
+    };
+    let stray_marker = lines.iter().enumerate().any(|(index, line)| {
+        (line.starts_with("<<<<<<<") || line.starts_with(">>>>>>>")) && !in_hunk(index + 1)
+    });
+    if stray_marker {
+        return Err("Note has malformed conflict markers; resolve it by hand".to_string());
+    }
+
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut resolved = String::with_capacity(content.len());
+    let mut next_line = 1;
+    for hunk in &hunks {
+        for line in &lines[next_line - 1..hunk.start_line - 1] {
+            resolved.push_str(line);
+        }
+        let sides: Vec<&str> = match choice {
+            "ours" => vec![&hunk.ours],
+            "theirs" => vec![&hunk.theirs],
+            "both" => vec![&hunk.ours, &hunk.theirs],
+            _ => return Err(format!("Unknown conflict choice: {}", choice)),
+        };
+        for side in sides.into_iter().filter(|side| !side.is_empty()) {
+            resolved.push_str(&side.replace('\n', newline));
+            resolved.push_str(newline);
+        }
+        next_line = hunk.end_line + 1;
+    }
+    for line in lines.iter().skip(next_line - 1) {
+        resolved.push_str(line);
+    }
+    Ok(resolved)
+}
+
+/// Resolve every merge conflict in a note by keeping "ours", "theirs" or "both", then
+/// stage the note if the vault is a git repository
+#[tauri::command]
+async fn resolve_conflict(
+    id: String,
+    choice: String,
+    state: State<'_, AppState>,
+) -> Result<Note, String> {
+    ensure_writable(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let root = PathBuf::from(&folder);
+
+    let file_path = abs_path_from_id(&root, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+    let resolved = resolve_conflict_hunks(&content, &choice)?;
+
+    let note = rewrite_note(&state, id, &file_path, resolved).await?;
+
+    if git::is_git_repo(&root) {
+        let staged = tauri::async_runtime::spawn_blocking(move || git::stage_file(&root, &file_path))
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(error) = staged.error {
+            return Err(format!("Conflict resolved, but staging the note failed: {}", error));
+        }
+    }
+    Ok(note)
+}
+
+/// Notes containing merge conflict markers (e.g. after a conflicted pull), with each
+/// conflicting block parsed out
+#[tauri::command]
+async fn list_conflicted_notes(
+    state: State<'_, AppState>,
+) -> Result<Vec<ConflictedNote>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    tokio::task::spawn_blocking(move || {
+        let mut conflicted: Vec<ConflictedNote> = load_vault_notes(&folder_path, scan)
+            .into_iter()
+            .filter(|(_, content)| content.contains("<<<<<<<"))
+            .filter_map(|(id, content)| {
+                let hunks = conflict_hunks(&content);
+                (!hunks.is_empty()).then_some(ConflictedNote { id, hunks })
+            })
+            .collect();
+        conflicted.sort_by(|a, b| a.id.cmp(&b.id));
+        conflicted
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Notes no other note links to. With `exclude_index_pages`, orphans that link out to
+/// other notes (hub/index pages, which are entry points by design) are left out too.
+#[tauri::command]
+async fn list_orphan_notes(
+    exclude_index_pages: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    let exclude_index_pages = exclude_index_pages.unwrap_or(false);
+    tokio::task::spawn_blocking(move || {
+        let notes = load_vault_notes(&folder_path, scan);
+        let resolver = link_resolver_for(&notes, title_fallback_length);
+
+        let mut linked: HashSet<String> = HashSet::new();
+        let mut links_out: HashSet<&str> = HashSet::new();
+        for (id, content) in &notes {
+            for link in links::extract_links(content) {
+                // A note linking to itself doesn't make it reachable
+                match resolver.resolve(&folder_path, id, &link) {
+                    Some(target) if target != *id => {
+                        links_out.insert(id.as_str());
+                        linked.insert(target);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut orphans: Vec<String> = notes
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| !linked.contains(*id))
+            .filter(|id| !(exclude_index_pages && links_out.contains(id.as_str())))
+            .cloned()
+            .collect();
+        orphans.sort();
+        orphans
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Every note and the note links between them, built in a single pass over the vault.
+/// Unresolvable note links are included as broken edges when `include_broken` is set.
+#[tauri::command]
+async fn link_graph(
+    include_broken: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<LinkGraph, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    let include_broken = include_broken.unwrap_or(false);
+    tokio::task::spawn_blocking(move || {
+        let mut notes = load_vault_notes(&folder_path, scan);
+        notes.sort_by(|a, b| a.0.cmp(&b.0));
+        let resolver = link_resolver_for(&notes, title_fallback_length);
+
+        let mut graph = LinkGraph::default();
+        let mut seen: HashSet<LinkGraphEdge> = HashSet::new();
+        for (id, content) in &notes {
+            graph.nodes.push(LinkGraphNode {
+                id: id.clone(),
+                title: extract_title(content, title_fallback_length),
+            });
+            for link in links::extract_links(content) {
+                if !link.is_note_link() {
+                    continue;
+                }
+                let edge = match resolver.resolve(&folder_path, id, &link) {
+                    Some(to) => LinkGraphEdge { from: id.clone(), to, broken: false },
+                    None if include_broken => LinkGraphEdge {
+                        from: id.clone(),
+                        to: link.target,
+                        broken: true,
+                    },
+                    None => continue,
+                };
+                if seen.insert(edge.clone()) {
+                    graph.edges.push(edge);
+                }
+            }
+        }
+        graph
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vault_disk_space(state: State<'_, AppState>) -> Result<DiskSpace, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let stats = fs4::statvfs(&folder).map_err(|e| format!("Failed to read disk space: {}", e))?;
+        Ok(DiskSpace {
+            available_bytes: stats.available_space(),
+            total_bytes: stats.total_space(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn resolve_asset_path(note_id: String, rel: String, state: State<AppState>) -> Result<String, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let resolved = resolve_note_relative_path(&folder_path, &note_id, &rel)?;
+
+    // Canonicalize both sides so a symlink can't point outside the vault
+    let canonical = resolved
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve asset path: {}", e))?;
+    let canonical_root = folder_path
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve notes folder: {}", e))?;
+    if !canonical.starts_with(&canonical_root) {
+        return Err("Path escapes notes folder".to_string());
+    }
+
+    Ok(canonical.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+async fn list_note_assets(id: String, state: State<'_, AppState>) -> Result<Vec<NoteAsset>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    let (content, _) = read_note_text(&file_path).await?;
+
+    let assets = extract_image_refs(&content)
+        .into_iter()
+        .filter_map(|rel| {
+            if is_external_ref(&rel) {
+                return Some(NoteAsset {
+                    rel,
+                    abs: None,
+                    exists: false,
+                    external: true,
+                });
+            }
+            // References that resolve outside the vault are skipped
+            let abs = resolve_note_relative_path(&folder_path, &id, &rel).ok()?;
+            Some(NoteAsset {
+                exists: abs.is_file(),
+                abs: Some(abs.to_string_lossy().into_owned()),
+                rel,
+                external: false,
+            })
+        })
+        .collect();
+
+    Ok(assets)
+}
+
+#[tauri::command]
+async fn get_vault_health(state: State<'_, AppState>) -> Result<VaultHealth, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    let index_doc_count = {
+        let index = state.search_index.lock().expect("search index mutex");
+        (*index).as_ref().map(|search_index| search_index.doc_count())
+    };
+
+    let scan = vault_note_scan(&state);
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    tokio::task::spawn_blocking(move || {
+        let mut health = VaultHealth {
+            notes_folder: folder.clone(),
+            index_present: index_doc_count.is_some(),
+            index_doc_count: index_doc_count.unwrap_or(0),
+            git_available: git::is_available(),
+            ..Default::default()
+        };
+
+        let folder_path = match folder {
+            Some(f) => PathBuf::from(f),
+            None => return health,
+        };
+
+        health.folder_exists = folder_path.is_dir();
+        if !health.folder_exists {
+            return health;
+        }
+        match check_folder_writable(&folder_path) {
+            Ok(()) => health.folder_writable = true,
+            Err(e) => health.write_error = Some(e),
+        }
+        health.git_repo = git::is_git_repo(&folder_path);
+
+        let notes = load_vault_notes(&folder_path, scan);
+        health.note_count = notes.len();
+
+        let resolver = link_resolver_for(&notes, title_fallback_length);
+
+        // Count unresolvable note links and remember every local file the notes reference
+        let mut referenced: HashSet<PathBuf> = HashSet::new();
+        for (id, content) in &notes {
+            for link in links::extract_links(content) {
+                if link.is_note_link() {
+                    if resolver.resolve(&folder_path, id, &link).is_none() {
+                        health.broken_link_count += 1;
+                    }
+                } else if let Ok(path) = resolve_note_relative_path(&folder_path, id, &link.target) {
+                    referenced.insert(path);
+                }
+            }
+            for rel in extract_image_refs(content) {
+                if is_external_ref(&rel) {
+                    continue;
+                }
+                if let Ok(path) = resolve_note_relative_path(&folder_path, id, &rel) {
+                    referenced.insert(path);
+                }
+            }
+        }
+
+        health.unused_asset_count = walkdir::WalkDir::new(folder_path.join("assets"))
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file() && !referenced.contains(entry.path()))
+            .count();
+
+        health
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn render_markdown(
+    content: String,
+    note_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    let notes_root = folder.map(PathBuf::from);
+    Ok(render_markdown_html(&content, notes_root.as_deref(), note_id.as_deref()))
+}
+
+#[tauri::command]
+async fn read_note(
+    id: String,
+    include_frontmatter: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Note, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    if !folder_path.is_dir() {
+        return Err(VAULT_UNAVAILABLE.to_string());
+    }
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+
+    let (content, encoding) = read_note_text(&file_path).await?;
+    let metadata = fs::metadata(&file_path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let modified = mtime_secs(&metadata);
+
+    remember_served_content(&state, &id, &content);
+
+    let title = extract_title(&content, vault_text_lengths(&state).title_fallback);
+    let overrides = note_overrides(&content);
+    let detected_direction = overrides
+        .as_ref()
+        .and_then(|o| o.text_direction.clone())
+        .filter(|d| d != "auto")
+        .or_else(|| detect_text_direction(strip_frontmatter(&content)).map(str::to_string));
+    // Only what's returned changes; save_note still takes (and writes) the full note
+    let (frontmatter, content) = match split_frontmatter(&content) {
+        Some((_, body_offset)) if !include_frontmatter.unwrap_or(true) => (
+            Some(content[..body_offset].to_string()),
+            content[body_offset..].to_string(),
+        ),
+        _ => (None, content),
+    };
+
+    Ok(Note {
+        id,
+        title,
+        content,
+        path: file_path.to_string_lossy().into_owned(),
+        modified,
+        modified_ms: mtime_millis(&metadata),
+        encoding: encoding.map(str::to_string),
+        frontmatter,
+        overrides,
+        detected_direction,
+    })
+}
+
+/// Read a note file as text. Files that aren't valid UTF-8 (e.g. Latin-1 imports) are
+/// decoded from their detected encoding, whose name is returned alongside.
+async fn read_note_text(path: &Path) -> Result<(String, Option<&'static str>), String> {
+    let bytes = fs::read(path).await.map_err(|e| e.to_string())?;
+    Ok(decode_note_bytes(bytes))
+}
+
+// Blocking read_note_text for scans (list, index, stats): non-UTF-8 notes are decoded
+// rather than skipped
+fn read_note_file(path: &Path) -> std::io::Result<String> {
+    std::fs::read(path).map(|bytes| decode_note_bytes(bytes).0)
+}
+
+fn decode_note_bytes(bytes: Vec<u8>) -> (String, Option<&'static str>) {
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        Err(e) => {
+            let bytes = e.into_bytes();
+            let mut detector = chardetng::EncodingDetector::new();
+            detector.feed(&bytes, true);
+            let encoding = detector.guess(None, false);
+            let (text, _, _) = encoding.decode(&bytes);
+            (text.into_owned(), Some(encoding.name()))
+        }
+    }
+}
+
+/// Rewrite a note that was decoded from another encoding as UTF-8
+#[tauri::command]
+async fn convert_note_to_utf8(id: String, state: State<'_, AppState>) -> Result<Note, String> {
+    ensure_writable(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, encoding) = read_note_text(&file_path).await?;
+    if encoding.is_none() {
+        return Err("The note is already UTF-8".to_string());
+    }
+
+    rewrite_note(&state, id, &file_path, content).await
+}
+
+#[tauri::command]
+async fn reload_note(id: String, state: State<'_, AppState>) -> Result<ReloadedNote, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+
+    let (content, encoding) = read_note_text(&file_path).await?;
+    let metadata = fs::metadata(&file_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let modified = mtime_secs(&metadata);
+    let title = extract_title(&content, vault_text_lengths(&state).title_fallback);
+
+    // The watcher may have missed this change, so refresh the index entry too
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(&id, &title, &content, modified);
+        }
+    }
+
+    let hash = content_hash(content.as_bytes());
+    let changed = {
+        let mut served = state.served_hashes.lock().expect("served hashes mutex");
+        served.insert(id.clone(), hash.clone()).as_ref() != Some(&hash)
+    };
+
+    Ok(ReloadedNote {
+        note: Note {
+            id,
+            title,
+            content,
+            path: file_path.to_string_lossy().into_owned(),
+            modified,
+            modified_ms: mtime_millis(&metadata),
+            encoding: encoding.map(str::to_string),
+            frontmatter: None,
+            overrides: None,
+            detected_direction: None,
+        },
+        changed,
+    })
+}
+
+#[tauri::command]
+async fn toggle_task(
+    id: String,
+    line: usize,
+    expected_text: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Note, String> {
+    ensure_writable(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+
+    let (content, _) = read_note_text(&file_path).await?;
+
+    // `line` is 1-based, matching list_tasks
+    let index = line
+        .checked_sub(1)
+        .filter(|&index| index < content.split_inclusive('\n').count())
+        .ok_or_else(|| format!("Line {} is out of range", line))?;
+    // Checkbox-looking lines inside fenced code aren't tasks
+    let checked = {
+        let (_, checked, text) = task_lines(&content)
+            .find(|&(i, _, _)| i == index)
+            .ok_or_else(|| format!("Line {} is not a task", line))?;
+        // The note may have changed since the task list was built
+        if let Some(ref expected) = expected_text {
+            if text != expected.trim() {
+                return Err(format!(
+                    "Line {} no longer contains the expected task",
+                    line
+                ));
+            }
+        }
+        checked
+    };
+
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(str::to_string).collect();
+    let target = &mut lines[index];
+
+    // The checkbox sits right after the list bullet: "<indent><bullet> [ ]"
+    let marker_start = target.len() - target.trim_start().len() + 2;
+    let marker = if checked { "[ ]" } else { "[x]" };
+    target.replace_range(marker_start..marker_start + 3, marker);
+
+    rewrite_note(&state, id, &file_path, lines.concat()).await
+}
+
+/// Write new content for an existing note in place (atomically), then update the search
+/// index and served-content hash. Used by commands that edit a note on the user's behalf.
+async fn rewrite_note(
+    state: &AppState,
+    id: String,
+    file_path: &Path,
+    content: String,
+) -> Result<Note, String> {
+    mark_self_write(state, file_path);
+    write_atomic(file_path, content.as_bytes())
+        .await
+        .map_err(describe_write_error)?;
+
+    let metadata = fs::metadata(file_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let modified = mtime_secs(&metadata);
+    let title = extract_title(&content, vault_text_lengths(&state).title_fallback);
+
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.index_note(&id, &title, &content, modified);
+        }
+    }
+
+    remember_served_content(state, &id, &content);
+
+    Ok(Note {
+        id,
+        title,
+        content,
+        path: file_path.to_string_lossy().into_owned(),
+        modified,
+        modified_ms: mtime_millis(&metadata),
+        encoding: None,
+        frontmatter: None,
+        overrides: None,
+        detected_direction: None,
+    })
+}
+
+/// Alternate names a note declares in its frontmatter (`aliases: [a, b]` or `aliases: a`)
+fn note_aliases(content: &str) -> Vec<String> {
+    let fields = match split_frontmatter(content).and_then(|(raw, _)| parse_frontmatter_yaml(raw)) {
+        Some(fields) => fields,
+        None => return Vec::new(),
+    };
+    let values = match fields.get("aliases") {
+        Some(serde_json::Value::Array(values)) => values.clone(),
+        Some(value) => vec![value.clone()],
+        None => return Vec::new(),
+    };
+    values
+        .iter()
+        .filter_map(|v| v.as_str())
+        .map(str::trim)
+        .filter(|alias| !alias.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// How many characters detect_text_direction looks at before giving up
+const DIRECTION_SAMPLE_CHARS: usize = 500;
+
+/// "rtl" or "ltr" for the first strong directional character (a letter) near the start
+/// of `text`, or None if there's no letter in the sample
+fn detect_text_direction(text: &str) -> Option<&'static str> {
+    let is_rtl = |c: char| {
+        matches!(c as u32,
+            0x0590..=0x08FF     // Hebrew, Arabic, Syriac, Thaana, NKo, Samaritan, ...
+            | 0xFB1D..=0xFDFF   // Hebrew and Arabic presentation forms
+            | 0xFE70..=0xFEFF
+            | 0x10800..=0x10FFF
+            | 0x1E800..=0x1EFFF)
+    };
+    text.chars()
+        .take(DIRECTION_SAMPLE_CHARS)
+        .find(|c| c.is_alphabetic())
+        .map(|c| if is_rtl(c) { "rtl" } else { "ltr" })
+}
+
+/// The note's frontmatter `scratch:` overrides, if it has any that are recognized.
+/// Text direction must be "ltr", "rtl" or "auto"; other values are ignored.
+fn note_overrides(content: &str) -> Option<NoteOverrides> {
+    let fields = split_frontmatter(content).and_then(|(raw, _)| parse_frontmatter_yaml(raw))?;
+    let block = fields.get("scratch")?.clone();
+    let mut overrides: NoteOverrides = serde_json::from_value(block).ok()?;
+    let trimmed = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    overrides.font = trimmed(overrides.font);
+    overrides.editor_width = trimmed(overrides.editor_width);
+    overrides.text_direction = trimmed(overrides.text_direction)
+        .map(|d| d.to_lowercase())
+        .filter(|d| matches!(d.as_str(), "ltr" | "rtl" | "auto"));
+
+    let empty = overrides.font.is_none()
+        && overrides.text_direction.is_none()
+        && overrides.editor_width.is_none();
+    (!empty).then_some(overrides)
+}
+
+/// Link resolver for a set of `(id, content)` notes, knowing their titles and aliases
+fn link_resolver_for(
+    notes: &[(String, String)],
+    title_fallback_length: usize,
+) -> links::LinkResolver {
+    let mut resolver = links::LinkResolver::new();
+    for (id, content) in notes {
+        resolver.add_note(id, &extract_title(content, title_fallback_length));
+    }
+    // After every title, so a note's name always wins over another note's alias
+    for (id, content) in notes {
+        for alias in note_aliases(content) {
+            resolver.add_alias(id, &alias);
+        }
+    }
+    resolver
+}
+
+/// Parse a frontmatter block as a YAML mapping. An empty block is an empty mapping.
+fn parse_frontmatter_yaml(raw: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    if raw.trim().is_empty() {
+        return Some(serde_json::Map::new());
+    }
+    match serde_yaml::from_str::<serde_json::Value>(raw) {
+        Ok(serde_json::Value::Object(map)) => Some(map),
+        _ => None,
+    }
+}
+
+#[tauri::command]
+async fn get_frontmatter(id: String, state: State<'_, AppState>) -> Result<Frontmatter, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    Ok(match split_frontmatter(&content) {
+        Some((raw, body_offset)) => {
+            let data = parse_frontmatter_yaml(raw);
+            Frontmatter {
+                error: data.is_none(),
+                data: data.map(serde_json::Value::Object),
+                raw: Some(raw.to_string()),
+                body_offset,
+            }
+        }
+        None => Frontmatter {
+            data: None,
+            raw: None,
+            body_offset: 0,
+            error: false,
+        },
+    })
+}
+
+/// Set one frontmatter field (creating the block if needed). A null value removes the
+/// field. The other fields keep their values and order and the body is untouched, but the
+/// block is re-serialized, so YAML comments, quoting style and anchors are not preserved.
+#[tauri::command]
+async fn set_frontmatter_field(
+    id: String,
+    key: String,
+    value: serde_json::Value,
+    state: State<'_, AppState>,
+) -> Result<Note, String> {
+    ensure_writable(&state)?;
+
+    if key.trim().is_empty() {
+        return Err("Frontmatter key is empty".to_string());
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    let (mut fields, body) = match split_frontmatter(&content) {
+        Some((raw, body_offset)) => {
+            // Don't rewrite a block we can't parse; that would drop whatever is in it
+            let fields = parse_frontmatter_yaml(raw)
+                .ok_or("The note's frontmatter isn't valid YAML; fix it by hand first")?;
+            (fields, &content[body_offset..])
+        }
+        None => (serde_json::Map::new(), content.as_str()),
+    };
+
+    if value.is_null() {
+        fields.remove(&key);
+    } else {
+        fields.insert(key, value);
+    }
+
+    let updated = if fields.is_empty() {
+        body.to_string()
+    } else {
+        let yaml = serde_yaml::to_string(&serde_json::Value::Object(fields))
+            .map_err(|e| format!("Failed to write frontmatter: {}", e))?;
+        format!("---\n{}---\n{}", yaml, body)
+    };
+
+    rewrite_note(&state, id, &file_path, updated).await
+}
+
+/// Normalize a tag given by the user ("#Work/Projects" -> "work/projects"), rejecting
+/// anything extract_tags wouldn't recognize as a whole tag
+fn normalize_tag_name(tag: &str) -> Result<String, String> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix('#').unwrap_or(tag);
+    let valid = !tag.is_empty()
+        && tag.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '/')
+        && tag.chars().any(char::is_alphabetic)
+        && !tag.starts_with('/')
+        && !tag.ends_with('/');
+    if !valid {
+        return Err(format!(
+            "\"{}\" isn't a valid tag (letters, digits, -, _ and / only, with at least one letter)",
+            tag
+        ));
+    }
+    Ok(tag.to_lowercase())
+}
+
+/// `tag` renamed from `old` to `new` if it is `old` or nested under it (`old/child`).
+/// Matching ignores case, like extract_tags.
+fn renamed_tag(tag: &str, old: &str, new: &str) -> Option<String> {
+    let lower = tag.to_lowercase();
+    if lower == old {
+        Some(new.to_string())
+    } else if lower.starts_with(old) && lower[old.len()..].starts_with('/') {
+        // Keep the child's case unless lowercasing changed byte lengths
+        let child = if tag.len() == lower.len() { &tag[old.len()..] } else { &lower[old.len()..] };
+        Some(format!("{}{}", new, child))
+    } else {
+        None
+    }
+}
+
+/// Rename tag `old` (and tags nested under it) to `new` in one note's inline tags and
+/// frontmatter `tags:`. Returns the new content and the number of tags renamed.
+fn rename_tag_in_content(content: &str, old: &str, new: &str) -> (String, usize) {
+    let mut result = content.to_string();
+    let mut count = 0;
+
+    // Replace back to front so earlier spans stay valid
+    for span in inline_tag_spans(content).into_iter().rev() {
+        if let Some(renamed) = renamed_tag(&content[span.clone()], old, new) {
+            result.replace_range(span, &renamed);
+            count += 1;
+        }
+    }
+
+    // Frontmatter is only rewritten when it parses and one of its tags changed
+    let Some((raw, body_offset)) = split_frontmatter(&result) else {
+        return (result, count);
+    };
+    let Some(mut fields) = parse_frontmatter_yaml(raw) else {
+        return (result, count);
+    };
+    let mut frontmatter_count = 0;
+    let mut rename_value = |value: &mut serde_json::Value| {
+        if let serde_json::Value::String(tag) = value {
+            let (hash, name) = match tag.strip_prefix('#') {
+                Some(name) => ("#", name),
+                None => ("", tag.as_str()),
+            };
+            if let Some(renamed) = renamed_tag(name.trim(), old, new) {
+                *tag = format!("{}{}", hash, renamed);
+                frontmatter_count += 1;
+            }
+        }
+    };
+    match fields.get_mut("tags") {
+        Some(serde_json::Value::Array(values)) => values.iter_mut().for_each(&mut rename_value),
+        Some(value) => rename_value(value),
+        None => {}
+    }
+    if frontmatter_count == 0 {
+        return (result, count);
+    }
+    match serde_yaml::to_string(&serde_json::Value::Object(fields)) {
+        Ok(yaml) => (
+            format!("---\n{}---\n{}", yaml, &result[body_offset..]),
+            count + frontmatter_count,
+        ),
+        Err(_) => (result, count),
+    }
+}
+
+/// Rename a tag across the vault: inline `#old` (outside code) and frontmatter `tags:`
+/// entries become `new`, and `#old/child` becomes `#new/child`. With `dry_run` nothing is
+/// written; either way the affected notes and per-note counts are returned.
+#[tauri::command]
+async fn rename_tag(
+    old: String,
+    new: String,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<TagRenameChange>, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        ensure_writable(&state)?;
+    }
+    let old = normalize_tag_name(&old)?;
+    let new = normalize_tag_name(&new)?;
+    if old == new {
+        return Err("The new tag is the same as the old one".to_string());
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let scan = vault_note_scan(&state);
+    let scan_path = folder_path.clone();
+    let notes = tokio::task::spawn_blocking(move || load_vault_notes(&scan_path, scan))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut changes = Vec::new();
+    for (id, content) in notes {
+        let (updated, count) = rename_tag_in_content(&content, &old, &new);
+        if count == 0 {
+            continue;
+        }
+        if !dry_run {
+            let file_path = abs_path_from_id(&folder_path, &id)?;
+            rewrite_note(&state, id.clone(), &file_path, updated).await?;
+        }
+        changes.push(TagRenameChange { id, count });
+    }
+    changes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(changes)
+}
+
+#[tauri::command]
+async fn read_note_range(
+    id: String,
+    start: usize,
+    len: usize,
+    state: State<'_, AppState>,
+) -> Result<NoteRange, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let mut file = std::fs::File::open(&file_path).map_err(|e| e.to_string())?;
+        let total_len = file.metadata().map_err(|e| e.to_string())?.len() as usize;
+        let mut start = start.min(total_len);
+
+        file.seek(SeekFrom::Start(start as u64))
+            .map_err(|e| e.to_string())?;
+        let mut buf = Vec::with_capacity(len.min(total_len - start));
+        (&mut file)
+            .take(len as u64)
+            .read_to_end(&mut buf)
+            .map_err(|e| e.to_string())?;
+
+        // Skip continuation bytes so the slice starts on a character boundary, and
+        // drop a trailing partial character cut off by the requested length
+        let split = |buf: &[u8]| -> Result<(usize, usize), String> {
+            let skip = buf.iter().take_while(|b| (**b & 0xC0) == 0x80).count();
+            let valid_len = match std::str::from_utf8(&buf[skip..]) {
+                Ok(s) => s.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err("Note is not valid UTF-8".to_string()),
+            };
+            Ok((skip, valid_len))
+        };
+        let (mut skip, mut valid_len) = split(&buf)?;
+
+        // A length shorter than the character at `start` would otherwise return nothing
+        // and leave `end == start`; extend the read until one whole character fits
+        if len > 0 && valid_len == 0 {
+            let mut byte = [0u8; 1];
+            while valid_len == 0 && buf.len() < len + 8 {
+                if file.read(&mut byte).map_err(|e| e.to_string())? == 0 {
+                    break;
+                }
+                buf.push(byte[0]);
+                (skip, valid_len) = split(&buf)?;
+            }
+        }
+
+        buf.truncate(skip + valid_len);
+        buf.drain(..skip);
+        start += skip;
+        let content = String::from_utf8(buf).map_err(|e| e.to_string())?;
+
+        Ok(NoteRange {
+            id,
+            end: start + content.len(),
+            content,
+            start,
+            total_len,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn save_note(
+    id: Option<String>,
+    content: String,
+    expected_modified: Option<i64>,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Note, NoteError> {
+    ensure_writable(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let lengths = vault_text_lengths(&state);
+    let title = extract_title(&content, lengths.title_fallback);
+    let sanitized_title = sanitize_filename(&title);
+    let strategy = collision_strategy(&state);
+    let rename_template = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .rename_template
+        .clone();
+
+    // Determine the file ID and path, handling renames
+    let (final_id, file_path, old_id) = if let Some(existing_id) = id {
+        // A note renamed by apply_rename_all keeps its templated form
+        let existing_leaf = existing_id.rsplit('/').next().unwrap_or(&existing_id);
+        let sanitized_leaf = rename_template
+            .as_deref()
+            .and_then(|template| templated_leaf(template, existing_leaf, &sanitized_title))
+            .unwrap_or_else(|| sanitized_title.clone());
+
+        // Preserve directory prefix for notes in subfolders
+        let (dir_prefix, desired_id) = if let Some(pos) = existing_id.rfind('/') {
+            let prefix = &existing_id[..pos];
+            (Some(prefix.to_string()), format!("{}/{}", prefix, sanitized_leaf))
+        } else {
+            (None, sanitized_leaf.clone())
+        };
+
+        let old_file_path = abs_path_from_id(&folder_path, &existing_id)?;
+
+        // Refuse to overwrite changes made on disk since the editor last loaded the note,
+        // unless the user explicitly chose to overwrite them
+        if let (Some(expected), false) = (expected_modified, force.unwrap_or(false)) {
+            let disk_modified = std::fs::metadata(&old_file_path)
+                .ok()
+                .map(|m| mtime_millis(&m));
+            if disk_modified != Some(expected) {
+                return Err(NoteError::Conflict {
+                    expected_modified: expected,
+                    disk_modified,
+                });
+            }
+        }
+
+        // A name that's already a unique version of the title (e.g. "Foo-1") is kept, so
+        // saving doesn't move the file around
+        if existing_id != desired_id && !is_collision_variant(existing_leaf, &sanitized_leaf) {
+            let mut new_id = desired_id.clone();
+            let mut counter = 1;
+
+            while new_id != existing_id
+                && abs_path_from_id(&folder_path, &new_id)
+                    .map(|p| p.exists())
+                    .unwrap_or(false)
+            {
+                let suffix = collision_suffix(strategy, &sanitized_leaf, counter);
+                new_id = if let Some(ref prefix) = dir_prefix {
+                    format!("{}/{}-{}", prefix, sanitized_leaf, suffix)
+                } else {
+                    format!("{}-{}", sanitized_leaf, suffix)
+                };
+                counter += 1;
+            }
+
+            let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
+            (new_id, new_file_path, Some((existing_id, old_file_path)))
+        } else {
+            (existing_id, old_file_path, None)
+        }
+    } else {
+        // New notes go in root
+        let sanitized_leaf = sanitized_title;
+        let mut new_id = sanitized_leaf.clone();
+        let mut counter = 1;
+
+        while abs_path_from_id(&folder_path, &new_id)
+            .map(|p| p.exists())
+            .unwrap_or(false)
+        {
+            new_id = format!(
+                "{}-{}",
+                sanitized_leaf,
+                collision_suffix(strategy, &sanitized_leaf, counter)
+            );
+            counter += 1;
+        }
+
+        let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
+        (new_id, new_file_path, None)
+    };
+
+    mark_self_write(&state, &file_path);
+    if let Some((_, ref old_file_path)) = old_id {
+        mark_self_write(&state, old_file_path);
+    }
+
+    // Write the file to the new path (recreating its folder if it was removed externally)
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    write_atomic(&file_path, content.as_bytes())
+        .await
+        .map_err(describe_write_error)?;
+
+    // Delete old file AFTER successful write (to prevent data loss)
+    if let Some((_, ref old_file_path)) = old_id {
+        if old_file_path.exists() && *old_file_path != file_path {
+            let _ = fs::remove_file(old_file_path).await;
+            // A title rename is now the latest change to the vault; undoing an older
+            // operation past it would be surprising, so drop the undo buffer
+            clear_undo(&state);
+        }
+    }
+
+    let metadata = fs::metadata(&file_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let modified = mtime_secs(&metadata);
+
+    // Update search index (delete old entry if renamed, then add new)
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            if let Some((ref old_id_str, _)) = old_id {
+                let _ = search_index.delete_note(old_id_str);
+            }
+            let _ = search_index.index_note(&final_id, &title, &content, modified);
+        }
+    }
+
+    // Update cache (remove old entry if renamed) so suggest sees the new title and tags
+    // before the next list_notes
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        if let Some((ref old_id_str, _)) = old_id {
+            cache.remove(old_id_str);
+        }
+        cache.insert(
+            final_id.clone(),
+            NoteMetadata {
+                id: final_id.clone(),
+                title: title.clone(),
+                preview: generate_preview(&content, lengths.preview),
+                modified,
+                content: None,
+                modified_ms: mtime_millis(&metadata),
+                size: metadata.len(),
+                tags: note_tags(&content),
+            },
+        );
+    }
+
+    remember_served_content(&state, &final_id, &content);
+
+    Ok(Note {
+        id: final_id,
+        title,
+        content,
+        path: file_path.to_string_lossy().into_owned(),
+        modified,
+        modified_ms: mtime_millis(&metadata),
+        encoding: None,
+        frontmatter: None,
+        overrides: None,
+        detected_direction: None,
+    })
+}
+
+#[tauri::command]
+async fn delete_note(
+    id: String,
+    to_trash: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    ensure_writable(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let folder_path = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if file_path.exists() {
+        mark_self_write(&state, &file_path);
+        if to_trash.unwrap_or(false) {
+            let trashed = move_to_trash(&folder_path, &format!("{}.md", id))?;
+            record_undo(&state, format!("Delete note {}", id), trashed, file_path);
+        } else {
+            fs::remove_file(&file_path)
+                .await
+                .map_err(|e| e.to_string())?;
+            clear_undo(&state);
+        }
+    }
+
+    // Update search index
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            let _ = search_index.delete_note(&id);
+        }
+    }
+
+    // Remove from cache
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        cache.remove(&id);
+    }
+
+    Ok(())
+}
+
+/// ATX headings (`# ` to `###### `) in the note body, skipping frontmatter and fenced
+/// code, with the byte range of each heading line in `content`
+fn note_headings(content: &str) -> Vec<(OutlineEntry, std::ops::Range<usize>)> {
+    let body_offset = content.len() - strip_frontmatter(content).len();
+    let fences = fenced_code_ranges(&content[body_offset..]);
+    let mut line_number = content[..body_offset].matches('\n').count();
+    let mut headings = Vec::new();
+    let mut offset = body_offset;
+
+    for line in content[body_offset..].split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        line_number += 1;
+        if fences.iter().any(|r| r.contains(&(line_start - body_offset))) {
+            continue;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        if !(1..=6).contains(&level) {
+            continue;
+        }
+        let rest = &line[level..];
+        if !(rest.starts_with([' ', '\t']) || rest.trim().is_empty()) {
+            continue;
+        }
+        let text = rest.trim().trim_end_matches('#').trim();
+        if text.is_empty() {
+            continue;
+        }
+        headings.push((
+            OutlineEntry {
+                level,
+                text: text.to_string(),
+                line: line_number,
+            },
+            line_start..offset,
+        ));
+    }
+    headings
+}
+
+/// The note's headings in document order, for an outline panel
+#[tauri::command]
+async fn note_outline(id: String, state: State<'_, AppState>) -> Result<Vec<OutlineEntry>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    Ok(note_headings(&content)
+        .into_iter()
+        .map(|(entry, _)| entry)
+        .collect())
+}
+
+/// Add `text` at the end of the section under the first heading matching `heading_text`
+/// (case-insensitive), i.e. before the next heading of the same or a higher level.
+/// `heading_text` may include the `#` markers ("## Tasks") to only match that level.
+#[tauri::command]
+async fn append_under_heading(
+    id: String,
+    heading_text: String,
+    text: String,
+    state: State<'_, AppState>,
+) -> Result<Note, String> {
+    ensure_writable(&state)?;
+
+    let wanted_level = heading_text.trim().chars().take_while(|c| *c == '#').count();
+    let wanted = heading_text.trim().trim_start_matches('#').trim().to_lowercase();
+    if wanted.is_empty() {
+        return Err("Heading text is empty".to_string());
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    let headings = note_headings(&content);
+    let index = headings
+        .iter()
+        .position(|(entry, _)| {
+            entry.text.to_lowercase() == wanted && (wanted_level == 0 || entry.level == wanted_level)
+        })
+        .ok_or_else(|| format!("Heading not found: {}", heading_text.trim()))?;
+    let (heading, heading_line) = &headings[index];
+    let section_end = headings[index + 1..]
+        .iter()
+        .find(|(entry, _)| entry.level <= heading.level)
+        .map(|(_, line)| line.start)
+        .unwrap_or(content.len());
+
+    // Insert after the section's last non-blank text, keeping the blank lines that
+    // separate it from the next heading
+    let section = &content[heading_line.end..section_end];
+    let insert_at = heading_line.end + section.trim_end().len();
+    let mut updated = content[..insert_at].to_string();
+    if !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(text.trim_end_matches(['\r', '\n']));
+    updated.push('\n');
+    let tail = &content[insert_at..];
+    updated.push_str(
+        tail.strip_prefix("\r\n")
+            .or_else(|| tail.strip_prefix('\n'))
+            .unwrap_or(tail),
+    );
+
+    rewrite_note(&state, id, &file_path, updated).await
+}
+
+/// Sections of `content` starting at ATX headings of exactly `level` (outside fenced
+/// code), as (heading text, section text without the heading line). Also returns the
+/// text before the first such heading.
+fn split_sections(content: &str, level: usize) -> (&str, Vec<(String, &str)>) {
+    let fences = fenced_code_ranges(content);
+    let marker = "#".repeat(level);
+    let mut starts: Vec<(usize, usize, String)> = Vec::new(); // (line start, body start, heading)
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if fences.iter().any(|r| r.contains(&line_start)) {
+            continue;
+        }
+        let heading = line
+            .strip_prefix(marker.as_str())
+            .filter(|rest| rest.starts_with([' ', '\t']))
+            .map(|rest| rest.trim().trim_end_matches('#').trim());
+        if let Some(heading) = heading {
+            starts.push((line_start, offset, heading.to_string()));
+        }
+    }
+
+    let preamble = &content[..starts.first().map(|s| s.0).unwrap_or(content.len())];
+    let sections = starts
+        .iter()
+        .enumerate()
+        .map(|(i, (_, body_start, heading))| {
+            let end = starts.get(i + 1).map(|s| s.0).unwrap_or(content.len());
+            (heading.clone(), &content[*body_start..end])
+        })
+        .collect();
+    (preamble, sections)
+}
+
+/// Split a note into one note per section at `at_heading_level` (1-6), created next to
+/// it and named after each heading. The text before the first heading stays in the
+/// original, or with `trash_original` goes to an "<name> index" note while the original
+/// is moved to the trash. Returns the IDs of the notes created.
+#[tauri::command]
+async fn split_note(
+    id: String,
+    at_heading_level: u8,
+    trash_original: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    ensure_writable(&state)?;
+
+    if !(1..=6).contains(&at_heading_level) {
+        return Err("Heading level must be between 1 and 6".to_string());
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    let (preamble, sections) = split_sections(&content, at_heading_level as usize);
+    if sections.is_empty() {
+        return Err(format!("The note has no level {} headings", at_heading_level));
+    }
+
+    let dir_prefix = id.rfind('/').map(|pos| &id[..pos + 1]).unwrap_or("");
+    let unique_id = |leaf: &str| {
+        let mut new_id = format!("{}{}", dir_prefix, leaf);
+        let mut counter = 1;
+        while abs_path_from_id(&folder_path, &new_id)
+            .map(|p| p.exists())
+            .unwrap_or(false)
+        {
+            new_id = format!("{}{}-{}", dir_prefix, leaf, counter);
+            counter += 1;
+        }
+        new_id
+    };
+
+    let trash_original = trash_original.unwrap_or(false);
+    let mut new_notes: Vec<(String, String)> = Vec::new();
+    if trash_original && !is_effectively_empty(preamble.trim()) {
+        let leaf = id.rsplit('/').next().unwrap_or(&id);
+        new_notes.push((format!("{} index", leaf), preamble.to_string()));
+    }
+    for (heading, body) in sections {
+        // Each section becomes a note whose title is its heading
+        new_notes.push((heading.clone(), format!("# {}\n{}", heading, body)));
+    }
+
+    let mut created_ids = Vec::with_capacity(new_notes.len());
+    for (name, note_content) in new_notes {
+        let new_id = unique_id(&sanitize_filename(&name));
+        let new_path = abs_path_from_id(&folder_path, &new_id)?;
+        mark_self_write(&state, &new_path);
+        write_atomic(&new_path, note_content.as_bytes())
+            .await
+            .map_err(describe_write_error)?;
+
+        let modified = fs::metadata(&new_path)
+            .await
+            .ok()
+            .map(|m| mtime_secs(&m))
+            .unwrap_or(0);
+        let title = extract_title(&note_content, vault_text_lengths(&state).title_fallback);
+        {
+            let index = state.search_index.lock().expect("search index mutex");
+            if let Some(ref search_index) = *index {
+                let _ = search_index.index_note(&new_id, &title, &note_content, modified);
+            }
+        }
+        created_ids.push(new_id);
+    }
+
+    // The original is only changed once every section is safely written
+    if trash_original {
+        mark_self_write(&state, &file_path);
+        move_to_trash(&folder_path, &format!("{}.md", id))?;
+        clear_undo(&state);
+        {
+            let index = state.search_index.lock().expect("search index mutex");
+            if let Some(ref search_index) = *index {
+                let _ = search_index.delete_note(&id);
+            }
+        }
+        state.notes_cache.write().expect("cache write lock").remove(&id);
+    } else {
+        rewrite_note(&state, id, &file_path, preamble.to_string()).await?;
+    }
+
+    Ok(created_ids)
+}
+
+// Target style for convert_links / convert_all_links ("wikilink" or "markdown")
+fn parse_link_style(to: &str) -> Result<links::LinkKind, String> {
+    match to {
+        "wikilink" => Ok(links::LinkKind::Wiki),
+        "markdown" => Ok(links::LinkKind::Markdown),
+        _ => Err(format!("Unknown link style \"{}\" (expected wikilink or markdown)", to)),
+    }
+}
+
+/// Every note's content plus the resolver and titles needed to convert its links
+async fn load_link_context(
+    folder_path: &Path,
+    scan: NoteScan,
+    title_fallback_length: usize,
+) -> Result<(Vec<(String, String)>, links::LinkResolver, HashMap<String, String>), String> {
+    let folder_path = folder_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let notes = load_vault_notes(&folder_path, scan);
+        let resolver = link_resolver_for(&notes, title_fallback_length);
+        let titles = notes
+            .iter()
+            .map(|(id, content)| (id.clone(), extract_title(content, title_fallback_length)))
+            .collect();
+        (notes, resolver, titles)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Rewrite one note's links between `[[id]]` and `[title](id.md)` forms. `to` is
+/// "wikilink" or "markdown".
+#[tauri::command]
+async fn convert_links(id: String, to: String, state: State<'_, AppState>) -> Result<Note, String> {
+    ensure_writable(&state)?;
+    let to = parse_link_style(&to)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    let (_, resolver, titles) = load_link_context(
+        &folder_path,
+        vault_note_scan(&state),
+        vault_text_lengths(&state).title_fallback,
+    )
+    .await?;
+    let updated = links::convert_links(&folder_path, &id, &content, &resolver, &titles, to)
+        .unwrap_or(content);
+
+    rewrite_note(&state, id, &file_path, updated).await
+}
+
+/// convert_links for every note in the vault. Returns the IDs of the notes that
+/// changed (or would change, with `dry_run`).
+#[tauri::command]
+async fn convert_all_links(
+    to: String,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let to = parse_link_style(&to)?;
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        ensure_writable(&state)?;
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let (notes, resolver, titles) = load_link_context(
+        &folder_path,
+        vault_note_scan(&state),
+        vault_text_lengths(&state).title_fallback,
+    )
+    .await?;
+
+    let mut changed_ids = Vec::new();
+    for (id, content) in notes {
+        let Some(updated) = links::convert_links(&folder_path, &id, &content, &resolver, &titles, to)
+        else {
+            continue;
+        };
+        if !dry_run {
+            let file_path = abs_path_from_id(&folder_path, &id)?;
+            rewrite_note(&state, id.clone(), &file_path, updated).await?;
+        }
+        changed_ids.push(id);
+    }
+    changed_ids.sort();
+
+    Ok(changed_ids)
+}
+
+#[tauri::command]
+async fn rename_folder(
+    app: AppHandle,
+    old_dir: String,
+    new_dir: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteRename>, String> {
+    ensure_writable(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let old_dir = validate_folder_id(&old_dir)?;
+    let new_dir = validate_folder_id(&new_dir)?;
+    if is_reserved_folder(&old_dir) || is_reserved_folder(&new_dir) {
+        return Err("Hidden folders and the assets folder cannot be renamed".to_string());
+    }
+    if old_dir == new_dir {
+        return Err("New folder name is the same as the old one".to_string());
+    }
+    if new_dir.starts_with(&format!("{}/", old_dir)) {
+        return Err("A folder cannot be moved inside itself".to_string());
+    }
+
+    let old_path = folder_path.join(&old_dir);
+    let new_path = folder_path.join(&new_dir);
+    if !old_path.is_dir() {
+        return Err(format!("Folder not found: {}", old_dir));
+    }
+    // On case-insensitive filesystems a case-only rename ("Work" -> "work") makes
+    // new_path resolve to the folder being renamed, which is fine
+    let same_dir = match (old_path.canonicalize(), new_path.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    if new_path.exists() && !same_dir {
+        return Err(format!("Folder already exists: {}", new_dir));
+    }
+
+    // Collect the notes under the old folder before moving it
+    let old_prefix = format!("{}/", old_dir);
+    let scan = vault_note_scan(&state);
+    let old_ids: Vec<String> = walk_notes(&folder_path, scan)
+        .map(|(id, _)| id)
+        .filter(|id| id.starts_with(&old_prefix))
+        .collect();
+
+    if let Some(parent) = new_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    fs::rename(&old_path, &new_path)
+        .await
+        .map_err(|e| format!("Failed to rename folder: {}", e))?;
+    record_undo(
+        &state,
+        format!("Rename folder {} to {}", old_dir, new_dir),
+        new_path.clone(),
+        old_path.clone(),
+    );
+
+    let renames: Vec<NoteRename> = old_ids
+        .into_iter()
+        .map(|old_id| {
+            let new_id = format!("{}/{}", new_dir, &old_id[old_prefix.len()..]);
+            NoteRename { old_id, new_id }
+        })
+        .collect();
+
+    // Re-index every moved note under its new ID
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            for rename in &renames {
+                let _ = search_index.delete_note(&rename.old_id);
+                let file_path = match abs_path_from_id(&folder_path, &rename.new_id) {
+                    Ok(p) => p,
+                    Err(_) => continue,
+                };
+                if let Ok(content) = read_note_file(&file_path) {
+                    let modified = std::fs::metadata(&file_path)
+                        .ok()
+                        .map(|m| mtime_secs(&m))
+                        .unwrap_or(0);
+                    let _ = search_index.index_note(
+                        &rename.new_id,
+                        &extract_title(&content, title_fallback_length),
+                        &content,
+                        modified,
+                    );
+                }
+            }
+        }
+    }
+
+    // Move cache entries to their new IDs
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        for rename in &renames {
+            if let Some(mut note) = cache.remove(&rename.old_id) {
+                note.id = rename.new_id.clone();
+                cache.insert(rename.new_id.clone(), note);
+            }
+        }
+    }
+
+    // Migrate pinned / last-opened note IDs
+    {
+        let id_map: HashMap<String, String> = renames
+            .iter()
+            .map(|r| (r.old_id.clone(), r.new_id.clone()))
+            .collect();
+        let mut settings = state.settings.write().expect("settings write lock");
+        if remap_note_ids_in_settings(&mut settings, &id_map) {
+            save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+        }
     }
 
-    // Initialize search index
-    if let Ok(index_path) = get_search_index_path(&app) {
-        if let Ok(search_index) = SearchIndex::new(&index_path) {
-            let _ = search_index.rebuild_index(&path_buf);
-            let mut index = state.search_index.lock().expect("search index mutex");
-            *index = Some(search_index);
+    // Let open editors pick up their new IDs
+    for rename in &renames {
+        if let Ok(new_file_path) = abs_path_from_id(&folder_path, &rename.new_id) {
+            let _ = app.emit(
+                "file-change",
+                FileChangeEvent {
+                    kind: "renamed".to_string(),
+                    path: new_file_path.to_string_lossy().into_owned(),
+                    changed_ids: vec![rename.old_id.clone(), rename.new_id.clone()],
+                },
+            );
         }
     }
 
-    Ok(())
+    Ok(renames)
 }
 
 #[tauri::command]
-async fn list_notes(state: State<'_, AppState>) -> Result<Vec<NoteMetadata>, String> {
+async fn normalize_filenames(
+    app: AppHandle,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<RenameOutcome, String> {
+    let dry_run = dry_run.unwrap_or(false);
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
@@ -795,92 +5285,289 @@ async fn list_notes(state: State<'_, AppState>) -> Result<Vec<NoteMetadata>, Str
             .clone()
             .ok_or("Notes folder not set")?
     };
+    let folder_path = PathBuf::from(&folder);
 
-    let path = PathBuf::from(&folder);
-    if !path.exists() {
-        return Ok(vec![]);
+    let scan = vault_note_scan(&state);
+    let mut notes = load_vault_notes(&folder_path, scan);
+    notes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    let plan: Vec<NoteRename> = plan_renames(&notes, collision_strategy(&state), |_, content| {
+        sanitize_filename(&extract_title(content, title_fallback_length))
+    })
+    .into_iter()
+    .map(|planned| NoteRename {
+        old_id: planned.old_id,
+        new_id: planned.new_id,
+    })
+    .collect();
+
+    if dry_run {
+        return Ok(RenameOutcome {
+            renamed: plan,
+            skipped: Vec::new(),
+        });
     }
+    ensure_writable(&state)?;
 
-    let path_clone = path.clone();
-    let discovered = tokio::task::spawn_blocking(move || {
-        use walkdir::WalkDir;
-        let mut results: Vec<(String, String, String, i64)> = Vec::new();
-        for entry in WalkDir::new(&path_clone)
-            .max_depth(10)
-            .into_iter()
-            .filter_entry(is_visible_notes_entry)
-            .flatten()
-        {
-            let file_path = entry.path();
-            if !file_path.is_file() {
-                continue;
+    apply_renames(&app, &state, &folder, &notes, plan).await
+}
+
+// A rename planned by plan_renames. `collision` is set when the wanted name was taken
+// and a collisionStrategy suffix was added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedRename {
+    pub old_id: String,
+    pub new_id: String,
+    pub collision: bool,
+}
+
+/// Plan renaming each of `notes` (sorted by ID) to the file name `leaf_for` gives it,
+/// staying in the same folder. Notes already named that way are left out.
+fn plan_renames(
+    notes: &[(String, String)],
+    strategy: CollisionStrategy,
+    leaf_for: impl Fn(&str, &str) -> String,
+) -> Vec<PlannedRename> {
+    // IDs are compared case-insensitively so the plan is safe on macOS/Windows too
+    let mut taken: HashSet<String> = notes.iter().map(|(id, _)| id.to_lowercase()).collect();
+    let mut plan: Vec<PlannedRename> = Vec::new();
+
+    for (id, content) in notes {
+        let leaf = leaf_for(id, content);
+        let (dir_prefix, current_leaf) = match id.rsplit_once('/') {
+            Some((dir, leaf)) => (Some(dir), leaf),
+            None => (None, id.as_str()),
+        };
+        if current_leaf == leaf {
+            continue;
+        }
+
+        let with_prefix = |leaf: String| match dir_prefix {
+            Some(dir) => format!("{}/{}", dir, leaf),
+            None => leaf,
+        };
+        let mut new_id = with_prefix(leaf.clone());
+        let mut counter = 1;
+        // The note's own ID doesn't count as a collision (case-only renames)
+        while new_id.to_lowercase() != id.to_lowercase() && taken.contains(&new_id.to_lowercase()) {
+            new_id = with_prefix(format!("{}-{}", leaf, collision_suffix(strategy, &leaf, counter)));
+            counter += 1;
+        }
+        if new_id == *id {
+            continue;
+        }
+
+        taken.insert(new_id.to_lowercase());
+        plan.push(PlannedRename {
+            old_id: id.clone(),
+            new_id,
+            collision: counter > 1,
+        });
+    }
+    plan
+}
+
+/// Carry out renames within their folders: move the files, point inbound links at the
+/// new names, and update the index, cache, settings and open editors. `notes` is the
+/// vault's `(id, content)` before any rename. A rename whose new name is already taken
+/// on disk (e.g. by a note the scan couldn't read) or that fails is skipped, never
+/// written over, and reported in `skipped`.
+async fn apply_renames(
+    app: &AppHandle,
+    state: &AppState,
+    folder: &str,
+    notes: &[(String, String)],
+    plan: Vec<NoteRename>,
+) -> Result<RenameOutcome, String> {
+    let folder_path = PathBuf::from(folder);
+
+    // Resolve links against the vault as it was before any rename
+    let title_fallback_length = vault_text_lengths(state).title_fallback;
+    let resolver = link_resolver_for(notes, title_fallback_length);
+
+    let mut renames: Vec<NoteRename> = Vec::new();
+    let mut skipped: Vec<SkippedRename> = Vec::new();
+    for rename in plan {
+        let old_path = abs_path_from_id(&folder_path, &rename.old_id)?;
+        let new_path = abs_path_from_id(&folder_path, &rename.new_id)?;
+        // A case-only rename on a case-insensitive filesystem finds the note itself
+        let same_file = match (old_path.canonicalize(), new_path.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        };
+        if new_path.exists() && !same_file {
+            skipped.push(SkippedRename {
+                reason: format!("{}.md already exists", rename.new_id),
+                old_id: rename.old_id,
+                new_id: rename.new_id,
+            });
+            continue;
+        }
+        mark_self_write(state, &old_path);
+        mark_self_write(state, &new_path);
+        if let Err(e) = fs::rename(&old_path, &new_path).await {
+            skipped.push(SkippedRename {
+                reason: e.to_string(),
+                old_id: rename.old_id,
+                new_id: rename.new_id,
+            });
+            continue;
+        }
+        renames.push(rename);
+    }
+    if !renames.is_empty() {
+        // Renamed files are no longer where the undo buffer expects them
+        clear_undo(state);
+    }
+
+    let id_map: HashMap<String, String> = renames
+        .iter()
+        .map(|r| (r.old_id.clone(), r.new_id.clone()))
+        .collect();
+
+    // Point inbound links at the new file names. Renames never change a note's folder,
+    // so relative links inside renamed notes stay valid as-is.
+    let mut rewritten: Vec<String> = Vec::new();
+    for (id, content) in notes {
+        if let Some(updated) = links::rewrite_links(&folder_path, id, content, &resolver, &id_map) {
+            let current_id = id_map.get(id).unwrap_or(id);
+            let file_path = abs_path_from_id(&folder_path, current_id)?;
+            mark_self_write(state, &file_path);
+            write_atomic(&file_path, updated.as_bytes())
+                .await
+                .map_err(describe_write_error)?;
+            rewritten.push(current_id.clone());
+        }
+    }
+
+    // Re-index renamed notes and notes whose links were rewritten
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            for rename in &renames {
+                let _ = search_index.delete_note(&rename.old_id);
             }
-            if let Some(id) = id_from_abs_path(&path_clone, file_path) {
-                if let Ok(content) = std::fs::read_to_string(file_path) {
-                    let modified = entry
-                        .metadata()
+            let changed_ids = renames.iter().map(|r| &r.new_id).chain(rewritten.iter());
+            for id in changed_ids {
+                let file_path = match abs_path_from_id(&folder_path, id) {
+                    Ok(p) => p,
+                    Err(_) => continue,
+                };
+                if let Ok(content) = read_note_file(&file_path) {
+                    let modified = std::fs::metadata(&file_path)
                         .ok()
-                        .and_then(|m| m.modified().ok())
-                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                        .map(|d| d.as_secs() as i64)
+                        .map(|m| mtime_secs(&m))
                         .unwrap_or(0);
-                    let title = extract_title(&content);
-                    let preview = generate_preview(&content);
-                    results.push((id, title, preview, modified));
+                    let title = extract_title(&content, title_fallback_length);
+                    let _ = search_index.index_note(id, &title, &content, modified);
                 }
             }
         }
-        results
-    })
-    .await
-    .map_err(|e| e.to_string())?;
-
-    let mut notes: Vec<NoteMetadata> = discovered
-        .into_iter()
-        .map(|(id, title, preview, modified)| NoteMetadata {
-            id,
-            title,
-            preview,
-            modified,
-        })
-        .collect();
-
-    // Load pinned note IDs from settings
-    let pinned_ids: HashSet<String> = {
-        let settings = state.settings.read().expect("settings read lock");
-        settings
-            .pinned_note_ids
-            .as_ref()
-            .map(|ids| ids.iter().cloned().collect())
-            .unwrap_or_default()
-    };
-
-    // Sort: pinned notes first (by date), then unpinned notes (by date)
-    notes.sort_by(|a, b| {
-        let a_pinned = pinned_ids.contains(&a.id);
-        let b_pinned = pinned_ids.contains(&b.id);
+    }
 
-        match (a_pinned, b_pinned) {
-            (true, false) => std::cmp::Ordering::Less,    // a pinned, b not -> a first
-            (false, true) => std::cmp::Ordering::Greater, // b pinned, a not -> b first
-            _ => b.modified.cmp(&a.modified),             // both same status -> sort by date (newest first)
+    {
+        let mut cache = state.notes_cache.write().expect("cache write lock");
+        for rename in &renames {
+            cache.remove(&rename.old_id);
         }
-    });
+        for id in &rewritten {
+            cache.remove(id);
+        }
+    }
 
-    // Update cache efficiently
     {
-        let mut cache = state.notes_cache.write().expect("cache write lock");
-        cache.clear();
-        for note in &notes {
-            cache.insert(note.id.clone(), note.clone());
+        let mut settings = state.settings.write().expect("settings write lock");
+        if remap_note_ids_in_settings(&mut settings, &id_map) {
+            save_settings(folder, &settings).map_err(|e| e.to_string())?;
         }
     }
 
-    Ok(notes)
+    for rename in &renames {
+        if let Ok(new_file_path) = abs_path_from_id(&folder_path, &rename.new_id) {
+            let _ = app.emit(
+                "file-change",
+                FileChangeEvent {
+                    kind: "renamed".to_string(),
+                    path: new_file_path.to_string_lossy().into_owned(),
+                    changed_ids: vec![rename.old_id.clone(), rename.new_id.clone()],
+                },
+            );
+        }
+    }
+
+    Ok(RenameOutcome {
+        renamed: renames,
+        skipped,
+    })
 }
 
-#[tauri::command]
-async fn read_note(id: String, state: State<'_, AppState>) -> Result<Note, String> {
+/// File name for a note under a rename_all template: `{title}` plus the note name tags,
+/// with the date/time tags taken from the note's modified time rather than now
+fn expand_rename_template(
+    template: &str,
+    title: &str,
+    modified: chrono::DateTime<chrono::Local>,
+) -> String {
+    let expanded = template
+        .replace("{title}", title)
+        .replace("{timestamp}", &modified.timestamp().to_string())
+        .replace("{date}", &modified.format("%Y-%m-%d").to_string())
+        .replace("{year}", &modified.format("%Y").to_string())
+        .replace("{month}", &modified.format("%m").to_string())
+        .replace("{day}", &modified.format("%d").to_string())
+        .replace("{time}", &modified.format("%H-%M-%S").to_string());
+    sanitize_filename(&expanded)
+}
+
+/// If `leaf` has the form of rename `template` (optionally with a collision suffix),
+/// the leaf it should have for the title `sanitized_title`: unchanged if the title part
+/// already matches, else the same leaf with the title part replaced
+fn templated_leaf(template: &str, leaf: &str, sanitized_title: &str) -> Option<String> {
+    let tag_re = regex::Regex::new(r"\{(title|timestamp|date|year|month|day|time)\}").ok()?;
+    let mut pattern = String::from("^(?P<before>");
+    let mut last = 0;
+    let mut has_title = false;
+    for tag in tag_re.captures_iter(template) {
+        let whole = tag.get(0)?;
+        pattern.push_str(&regex::escape(&template[last..whole.start()]));
+        pattern.push_str(match &tag[1] {
+            "title" if !has_title => {
+                has_title = true;
+                ")(?P<title>.+?)(?P<after>"
+            }
+            "title" => ".+?",
+            "timestamp" => r"\d+",
+            "date" => r"\d{4}-\d{2}-\d{2}",
+            "year" => r"\d{4}",
+            "month" | "day" => r"\d{2}",
+            _ => r"\d{2}-\d{2}-\d{2}",
+        });
+        last = whole.end();
+    }
+    pattern.push_str(&regex::escape(&template[last..]));
+    pattern.push_str(r")(?:-[0-9a-f]{6,}(?:-\d+)?|-\d+)?$");
+
+    let caps = regex::Regex::new(&pattern).ok()?.captures(leaf)?;
+    if !has_title {
+        // The name doesn't depend on the title
+        return Some(leaf.to_string());
+    }
+    if &caps["title"] == sanitized_title {
+        return Some(leaf.to_string());
+    }
+    Some(format!("{}{}{}", &caps["before"], sanitized_title, &caps["after"]))
+}
+
+/// Plan renaming every note per `template` (e.g. "{date}-{title}")
+async fn plan_rename_all(
+    template: &str,
+    state: &AppState,
+) -> Result<(String, Vec<(String, String)>, Vec<PlannedRename>), String> {
+    if template.trim().is_empty() {
+        return Err("Rename template is empty".to_string());
+    }
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
@@ -888,42 +5575,81 @@ async fn read_note(id: String, state: State<'_, AppState>) -> Result<Note, Strin
             .clone()
             .ok_or("Notes folder not set")?
     };
-
     let folder_path = PathBuf::from(&folder);
-    let file_path = abs_path_from_id(&folder_path, &id)?;
-    if !file_path.exists() {
-        return Err("Note not found".to_string());
-    }
 
-    let content = fs::read_to_string(&file_path)
-        .await
-        .map_err(|e| e.to_string())?;
-    let metadata = fs::metadata(&file_path)
-        .await
-        .map_err(|e| e.to_string())?;
+    let scan = vault_note_scan(state);
+    let title_fallback_length = vault_text_lengths(state).title_fallback;
+    let strategy = collision_strategy(state);
+    let template = template.trim().to_string();
+    let scan_path = folder_path.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut notes = load_vault_notes(&scan_path, scan);
+        notes.sort_by(|a, b| a.0.cmp(&b.0));
+        let plan = plan_renames(&notes, strategy, |id, content| {
+            let modified = abs_path_from_id(&scan_path, id)
+                .ok()
+                .and_then(|path| std::fs::metadata(path).ok())
+                .and_then(|m| m.modified().ok())
+                .map(chrono::DateTime::<chrono::Local>::from)
+                .unwrap_or_else(chrono::Local::now);
+            let title = extract_title(content, title_fallback_length);
+            expand_rename_template(&template, &title, modified)
+        });
+        (folder, notes, plan)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
 
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+/// What every note would be renamed to under `template`, without changing anything.
+/// `{title}` is the note's title; the date/time tags use its modified time. Notes keep
+/// their folder, and names that are taken get a collisionStrategy suffix (`collision`).
+#[tauri::command]
+async fn preview_rename_all(
+    template: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<PlannedRename>, String> {
+    let (_, _, plan) = plan_rename_all(&template, &state).await?;
+    Ok(plan)
+}
 
-    Ok(Note {
-        id,
-        title: extract_title(&content),
-        content,
-        path: file_path.to_string_lossy().into_owned(),
-        modified,
-    })
+/// Rename every note per `template` as previewed by preview_rename_all, updating links,
+/// the index, the cache and pinned notes
+#[tauri::command]
+async fn apply_rename_all(
+    app: AppHandle,
+    template: String,
+    state: State<'_, AppState>,
+) -> Result<RenameOutcome, String> {
+    ensure_writable(&state)?;
+    let (folder, notes, plan) = plan_rename_all(&template, &state).await?;
+    let plan = plan
+        .into_iter()
+        .map(|planned| NoteRename {
+            old_id: planned.old_id,
+            new_id: planned.new_id,
+        })
+        .collect();
+    let outcome = apply_renames(&app, &state, &folder, &notes, plan).await?;
+
+    // Remembered so save_note keeps the new names' form when a title changes
+    {
+        let mut settings = state.settings.write().expect("settings write lock");
+        settings.rename_template = Some(template.trim().to_string());
+        save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+    }
+    Ok(outcome)
 }
 
 #[tauri::command]
-async fn save_note(
-    id: Option<String>,
-    content: String,
+async fn delete_folder(
+    dir: String,
+    to_trash: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<Note, String> {
+) -> Result<Vec<String>, String> {
+    ensure_writable(&state)?;
+    let to_trash = to_trash.unwrap_or(false);
+
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
@@ -933,110 +5659,60 @@ async fn save_note(
     };
     let folder_path = PathBuf::from(&folder);
 
-    let title = extract_title(&content);
-    let sanitized_leaf = sanitize_filename(&title);
-
-    // Determine the file ID and path, handling renames
-    let (final_id, file_path, old_id) = if let Some(existing_id) = id {
-        // Preserve directory prefix for notes in subfolders
-        let (dir_prefix, desired_id) = if let Some(pos) = existing_id.rfind('/') {
-            let prefix = &existing_id[..pos];
-            (Some(prefix.to_string()), format!("{}/{}", prefix, sanitized_leaf))
-        } else {
-            (None, sanitized_leaf.clone())
-        };
-
-        let old_file_path = abs_path_from_id(&folder_path, &existing_id)?;
+    // validate_folder_id rejects the vault root (empty path) and traversal
+    let dir = validate_folder_id(&dir)?;
+    if is_reserved_folder(&dir) {
+        return Err("Hidden folders and the assets folder cannot be deleted".to_string());
+    }
 
-        if existing_id != desired_id {
-            let mut new_id = desired_id.clone();
-            let mut counter = 1;
+    let dir_path = folder_path.join(&dir);
+    if !dir_path.is_dir() {
+        return Err(format!("Folder not found: {}", dir));
+    }
 
-            while new_id != existing_id
-                && abs_path_from_id(&folder_path, &new_id)
-                    .map(|p| p.exists())
-                    .unwrap_or(false)
-            {
-                new_id = if let Some(ref prefix) = dir_prefix {
-                    format!("{}/{}-{}", prefix, sanitized_leaf, counter)
-                } else {
-                    format!("{}-{}", sanitized_leaf, counter)
-                };
-                counter += 1;
-            }
+    let prefix = format!("{}/", dir);
+    let scan = vault_note_scan(&state);
+    let deleted_ids: Vec<String> = walk_notes(&folder_path, scan)
+        .map(|(id, _)| id)
+        .filter(|id| id.starts_with(&prefix))
+        .collect();
 
-            let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
-            (new_id, new_file_path, Some((existing_id, old_file_path)))
-        } else {
-            (existing_id, old_file_path, None)
-        }
+    if to_trash {
+        let trashed = move_to_trash(&folder_path, &dir)?;
+        record_undo(&state, format!("Delete folder {}", dir), trashed, dir_path);
     } else {
-        // New notes go in root
-        let mut new_id = sanitized_leaf.clone();
-        let mut counter = 1;
-
-        while abs_path_from_id(&folder_path, &new_id)
-            .map(|p| p.exists())
-            .unwrap_or(false)
-        {
-            new_id = format!("{}-{}", sanitized_leaf, counter);
-            counter += 1;
-        }
-
-        let new_file_path = abs_path_from_id(&folder_path, &new_id)?;
-        (new_id, new_file_path, None)
-    };
-
-    // Write the file to the new path
-    fs::write(&file_path, &content)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    // Delete old file AFTER successful write (to prevent data loss)
-    if let Some((_, ref old_file_path)) = old_id {
-        if old_file_path.exists() && *old_file_path != file_path {
-            let _ = fs::remove_file(old_file_path).await;
-        }
+        fs::remove_dir_all(&dir_path)
+            .await
+            .map_err(|e| format!("Failed to delete folder: {}", e))?;
+        // A permanent delete can't be undone, so don't let undo reach past it
+        clear_undo(&state);
     }
 
-    let metadata = fs::metadata(&file_path)
-        .await
-        .map_err(|e| e.to_string())?;
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
-
-    // Update search index (delete old entry if renamed, then add new)
+    // Purge deleted notes from the search index
     {
         let index = state.search_index.lock().expect("search index mutex");
         if let Some(ref search_index) = *index {
-            if let Some((ref old_id_str, _)) = old_id {
-                let _ = search_index.delete_note(old_id_str);
+            for id in &deleted_ids {
+                let _ = search_index.delete_note(id);
             }
-            let _ = search_index.index_note(&final_id, &title, &content, modified);
         }
     }
 
-    // Update cache (remove old entry if renamed)
-    if let Some((ref old_id_str, _)) = old_id {
+    // Remove from cache
+    {
         let mut cache = state.notes_cache.write().expect("cache write lock");
-        cache.remove(old_id_str);
+        for id in &deleted_ids {
+            cache.remove(id);
+        }
     }
 
-    Ok(Note {
-        id: final_id,
-        title,
-        content,
-        path: file_path.to_string_lossy().into_owned(),
-        modified,
-    })
+    Ok(deleted_ids)
 }
 
 #[tauri::command]
-async fn delete_note(id: String, state: State<'_, AppState>) -> Result<(), String> {
+async fn undo_last_operation(state: State<'_, AppState>) -> Result<UndoResult, String> {
+    ensure_writable(&state)?;
+
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
@@ -1044,34 +5720,125 @@ async fn delete_note(id: String, state: State<'_, AppState>) -> Result<(), Strin
             .clone()
             .ok_or("Notes folder not set")?
     };
-
     let folder_path = PathBuf::from(&folder);
-    let file_path = abs_path_from_id(&folder_path, &id)?;
-    if file_path.exists() {
-        fs::remove_file(&file_path)
+
+    let operation = state
+        .last_operation
+        .lock()
+        .expect("last operation mutex")
+        .take()
+        .ok_or("Nothing to undo")?;
+
+    if operation.notes_root != folder_path {
+        return Err("Nothing to undo".to_string());
+    }
+    if !operation.from.exists() {
+        return Err(format!("Cannot undo \"{}\": the item no longer exists", operation.description));
+    }
+    if operation.to.exists() {
+        return Err(format!(
+            "Cannot undo \"{}\": something already exists at the original location",
+            operation.description
+        ));
+    }
+
+    // Pair up note IDs at the current location with their IDs after moving back
+    let scan = vault_note_scan(&state);
+    let files: Vec<PathBuf> = if operation.from.is_dir() {
+        walkdir::WalkDir::new(&operation.from)
+            .max_depth(scan.max_depth)
+            .follow_links(scan.follow_symlinks)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect()
+    } else {
+        vec![operation.from.clone()]
+    };
+    let id_pairs: Vec<(Option<String>, Option<String>)> = files
+        .iter()
+        .filter_map(|file| {
+            let sub = file.strip_prefix(&operation.from).ok()?;
+            let restored = if sub.as_os_str().is_empty() {
+                operation.to.clone()
+            } else {
+                operation.to.join(sub)
+            };
+            Some((
+                id_from_abs_path(&folder_path, file),
+                id_from_abs_path(&folder_path, &restored),
+            ))
+        })
+        .collect();
+
+    if let Some(parent) = operation.to.parent() {
+        fs::create_dir_all(parent)
             .await
             .map_err(|e| e.to_string())?;
     }
+    fs::rename(&operation.from, &operation.to)
+        .await
+        .map_err(|e| format!("Failed to undo \"{}\": {}", operation.description, e))?;
 
-    // Update search index
+    let removed_ids: Vec<String> = id_pairs.iter().filter_map(|(old, _)| old.clone()).collect();
+    let restored_ids: Vec<String> = id_pairs.iter().filter_map(|(_, new)| new.clone()).collect();
+
+    // Re-index: drop the IDs that moved away, add the restored ones
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
     {
         let index = state.search_index.lock().expect("search index mutex");
         if let Some(ref search_index) = *index {
-            let _ = search_index.delete_note(&id);
+            for id in &removed_ids {
+                let _ = search_index.delete_note(id);
+            }
+            for id in &restored_ids {
+                let file_path = match abs_path_from_id(&folder_path, id) {
+                    Ok(p) => p,
+                    Err(_) => continue,
+                };
+                if let Ok(content) = read_note_file(&file_path) {
+                    let modified = std::fs::metadata(&file_path)
+                        .ok()
+                        .map(|m| mtime_secs(&m))
+                        .unwrap_or(0);
+                    let title = extract_title(&content, title_fallback_length);
+                    let _ = search_index.index_note(id, &title, &content, modified);
+                }
+            }
         }
     }
 
-    // Remove from cache
     {
         let mut cache = state.notes_cache.write().expect("cache write lock");
-        cache.remove(&id);
+        for id in &removed_ids {
+            cache.remove(id);
+        }
     }
 
-    Ok(())
+    // Point pins / last-opened note back at the restored IDs
+    {
+        let id_map: HashMap<String, String> = id_pairs
+            .into_iter()
+            .filter_map(|(old, new)| Some((old?, new?)))
+            .collect();
+        let mut settings = state.settings.write().expect("settings write lock");
+        if remap_note_ids_in_settings(&mut settings, &id_map) {
+            save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(UndoResult {
+        description: operation.description,
+        removed_ids,
+        restored_ids,
+    })
 }
 
 #[tauri::command]
 async fn create_note(state: State<'_, AppState>) -> Result<Note, String> {
+    ensure_writable(&state)?;
+
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
@@ -1090,22 +5857,26 @@ async fn create_note(state: State<'_, AppState>) -> Result<Note, String> {
             .unwrap_or_else(|| "Untitled".to_string())
     };
 
+    // Handle {counter} / {counter:03} tag (normalized to {counter} before expanding)
+    let (template, counter_width) = parse_counter_tag(&template);
+    let has_counter = counter_width.is_some();
+    let counter_width = counter_width.unwrap_or(0);
+
     // Expand template tags
     let expanded = expand_note_name_template(&template);
 
     // Sanitize filename
     let sanitized = sanitize_filename(&expanded);
 
-    // Handle {counter} tag
-    let has_counter = template.contains("{counter}");
     let base_id = if has_counter {
-        sanitized.replace("{counter}", "1")
+        sanitized.replace("{counter}", &format_counter(1, counter_width))
     } else {
         sanitized.clone()
     };
 
     let mut final_id = base_id.clone();
     let mut counter = if has_counter { 2 } else { 1 };
+    let strategy = collision_strategy(&state);
 
     // Ensure filename uniqueness
     while abs_path_from_id(&folder_path, &final_id)
@@ -1113,9 +5884,9 @@ async fn create_note(state: State<'_, AppState>) -> Result<Note, String> {
         .unwrap_or(false)
     {
         if has_counter {
-            final_id = sanitized.replace("{counter}", &counter.to_string());
+            final_id = sanitized.replace("{counter}", &format_counter(counter, counter_width));
         } else {
-            final_id = format!("{}-{}", base_id, counter);
+            final_id = format!("{}-{}", base_id, collision_suffix(strategy, &base_id, counter));
         }
         counter += 1;
     }
@@ -1133,6 +5904,7 @@ async fn create_note(state: State<'_, AppState>) -> Result<Note, String> {
             .map_err(|e| e.to_string())?;
     }
 
+    mark_self_write(&state, &file_path);
     fs::write(&file_path, &content)
         .await
         .map_err(|e| e.to_string())?;
@@ -1141,6 +5913,10 @@ async fn create_note(state: State<'_, AppState>) -> Result<Note, String> {
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
+    let modified_ms = fs::metadata(&file_path)
+        .await
+        .map(|m| mtime_millis(&m))
+        .unwrap_or(modified * 1000);
 
     // Update search index
     {
@@ -1156,30 +5932,208 @@ async fn create_note(state: State<'_, AppState>) -> Result<Note, String> {
         content,
         path: file_path.to_string_lossy().into_owned(),
         modified,
+        modified_ms,
+        encoding: None,
+        frontmatter: None,
+        overrides: None,
+        detected_direction: None,
     })
 }
 
+const DEFAULT_CAPTURE_NOTE_ID: &str = "{date}";
+
+/// Append `text` as a timestamped list item ("- 14:05 text") to the capture note
+/// (captureNoteId), creating the note if needed. Returns the capture note's ID.
+#[tauri::command]
+async fn quick_capture(text: String, state: State<'_, AppState>) -> Result<String, String> {
+    ensure_writable(&state)?;
+
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Nothing to capture".to_string());
+    }
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let template = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings
+            .capture_note_id
+            .clone()
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_CAPTURE_NOTE_ID.to_string())
+    };
+    let id = expand_note_name_template(template.trim());
+    let id = id.strip_suffix(".md").unwrap_or(&id).to_string();
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+
+    let existing = if file_path.exists() {
+        read_note_text(&file_path).await?.0
+    } else {
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        format!("# {}\n\n", extract_title_from_id(&id))
+    };
+
+    // Continuation lines are indented so they stay part of the list item
+    let time = chrono::Local::now().format("%H:%M");
+    let entry = text.lines().collect::<Vec<_>>().join("\n  ");
+    let mut content = existing;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!("- {} {}\n", time, entry));
+
+    rewrite_note(&state, id.clone(), &file_path, content).await?;
+    Ok(id)
+}
+
+// Indexed tokens depend on the analyzer, so a language or hashing change needs a full
+// rebuild; so does a depth change, which adds or drops notes, and a title length change,
+// which alters the indexed titles
+fn settings_need_rebuild(old: &Settings, new: &Settings) -> bool {
+    text_lengths(old).title_fallback != text_lengths(new).title_fallback
+        || old.search_language != new.search_language
+        || old.search_stop_words != new.search_stop_words
+        || note_scan(old) != note_scan(new)
+        || old.encrypt_index_content.unwrap_or(false) != new.encrypt_index_content.unwrap_or(false)
+}
+
 #[tauri::command]
 fn get_settings(state: State<AppState>) -> Settings {
     state.settings.read().expect("settings read lock").clone()
 }
 
 #[tauri::command]
-fn update_settings(
+async fn update_settings(
+    app: AppHandle,
     new_settings: Settings,
-    state: State<AppState>,
+    state: State<'_, AppState>,
 ) -> Result<(), String> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone().ok_or("Notes folder not set")?
     };
+    ensure_vault_lock(&state)?;
 
+    // Only a changed template is checked, so a vault whose saved template predates the
+    // rules can still save its other settings
+    let current_template = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .default_note_name
+        .clone();
+    if let Some(template) = new_settings
+        .default_note_name
+        .as_ref()
+        .filter(|template| current_template.as_ref() != Some(*template))
     {
+        let validation = validate_note_template(template.clone());
+        if !validation.valid {
+            return Err(format!(
+                "Invalid default note name: {}",
+                validation.warnings.join("; ")
+            ));
+        }
+    }
+
+    let mut new_settings = new_settings;
+    let (needs_rebuild, lengths_changed) = {
         let mut settings = state.settings.write().expect("settings write lock");
+        // The salt is backend-managed; keep it, and create one the first time
+        // encryptIndexContent is turned on
+        if new_settings.index_salt.is_none() {
+            new_settings.index_salt = settings.index_salt.clone();
+        }
+        if new_settings.encrypt_index_content.unwrap_or(false) && new_settings.index_salt.is_none() {
+            new_settings.index_salt = Some(generate_index_salt()?);
+        }
+        let needs_rebuild = settings_need_rebuild(&settings, &new_settings);
+        let lengths_changed = text_lengths(&settings) != text_lengths(&new_settings);
+        // lastOpenedNoteId is owned by set_last_opened_note; the frontend may send a stale copy
+        let last_opened_note_id = settings.last_opened_note_id.take();
+        // Likewise renameTemplate is owned by apply_rename_all
+        let rename_template = settings.rename_template.take();
         *settings = new_settings;
+        settings.last_opened_note_id = last_opened_note_id;
+        settings.rename_template = rename_template;
+        save_settings(&folder, &settings).map_err(|e| e.to_string())?;
+        (needs_rebuild, lengths_changed)
+    };
+
+    // list_notes reuses cached titles/previews for unchanged files; recompute them all
+    if lengths_changed {
+        state.notes_cache.write().expect("cache write lock").clear();
+    }
+
+    if needs_rebuild {
+        rebuild_search_index(app, state).await?;
+    }
+
+    Ok(())
+}
+
+/// Re-read `.scratch/settings.json` after it was changed outside the app (e.g. by sync)
+/// and make it the settings in effect
+#[tauri::command]
+async fn reload_settings(app: AppHandle, state: State<'_, AppState>) -> Result<Settings, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+    };
+
+    let new_settings = load_settings(&folder);
+    let (needs_rebuild, lengths_changed) = {
+        let mut settings = state.settings.write().expect("settings write lock");
+        let needs_rebuild = settings_need_rebuild(&settings, &new_settings);
+        let lengths_changed = text_lengths(&settings) != text_lengths(&new_settings);
+        *settings = new_settings.clone();
+        (needs_rebuild, lengths_changed)
+    };
+
+    if lengths_changed {
+        state.notes_cache.write().expect("cache write lock").clear();
+    }
+    if needs_rebuild {
+        rebuild_search_index(app, state).await?;
     }
 
+    Ok(new_settings)
+}
+
+#[tauri::command]
+fn get_read_only(state: State<AppState>) -> bool {
     let settings = state.settings.read().expect("settings read lock");
+    settings.read_only.unwrap_or(false)
+}
+
+#[tauri::command]
+fn set_last_opened_note(id: Option<String>, state: State<AppState>) -> Result<(), String> {
+    ensure_vault_lock(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+    };
+
+    // Validate the ID before persisting it
+    if let Some(ref note_id) = id {
+        abs_path_from_id(Path::new(&folder), note_id)?;
+    }
+
+    let mut settings = state.settings.write().expect("settings write lock");
+    settings.last_opened_note_id = id;
     save_settings(&folder, &settings).map_err(|e| e.to_string())?;
 
     Ok(())
@@ -1194,17 +6148,91 @@ async fn write_file(path: String, contents: Vec<u8>) -> Result<(), String> {
 
 #[tauri::command]
 fn preview_note_name(template: String) -> Result<String, String> {
+    Ok(note_name_preview(&template))
+}
+
+// The first note name `template` produces (with {counter} as 1 if present)
+fn note_name_preview(template: &str) -> String {
+    let (template, counter_width) = parse_counter_tag(template);
     let expanded = expand_note_name_template(&template);
     let sanitized = sanitize_filename(&expanded);
+    match counter_width {
+        Some(width) => sanitized.replace("{counter}", &format_counter(1, width)),
+        None => sanitized,
+    }
+}
 
-    // Show first note name (with counter as 1 if present)
-    let preview = if template.contains("{counter}") {
-        sanitized.replace("{counter}", "1")
-    } else {
-        sanitized
+/// Find the `{counter}` tag, optionally with a zero-padded width (`{counter:03}` gives
+/// 001, 002, ...). Returns the template with the tag normalized to `{counter}` (so
+/// sanitizing doesn't mangle the `:`) and the width, 0 for unpadded, if it's present.
+fn parse_counter_tag(template: &str) -> (String, Option<usize>) {
+    let counter_re = regex::Regex::new(r"\{counter(?::(\d{1,2}))?\}").unwrap();
+    let width = match counter_re.captures(template) {
+        Some(caps) => caps
+            .get(1)
+            .and_then(|w| w.as_str().parse().ok())
+            .unwrap_or(0),
+        None => return (template.to_string(), None),
     };
+    (counter_re.replace_all(template, "{counter}").into_owned(), Some(width))
+}
+
+fn format_counter(counter: usize, width: usize) -> String {
+    format!("{:0width$}", counter, width = width)
+}
+
+// Tags expand_note_name_template and create_note understand
+const NOTE_NAME_TAGS: &[&str] = &["timestamp", "date", "year", "month", "day", "time", "counter"];
+
+// validate_note_template result. `valid` is false when note creation would not use the
+// template as written; `warnings` explains why, plus smaller surprises.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteTemplateValidation {
+    pub valid: bool,
+    pub expanded: String,
+    pub warnings: Vec<String>,
+}
+
+/// Check a defaultNoteName template: unknown `{tags}` and names that come out empty or
+/// hidden are invalid; characters that get replaced in file names are warned about.
+#[tauri::command]
+fn validate_note_template(template: String) -> NoteTemplateValidation {
+    let mut valid = true;
+    let mut warnings = Vec::new();
+
+    let tag_re = regex::Regex::new(r"\{([^{}]*)\}").unwrap();
+    for caps in tag_re.captures_iter(&template) {
+        let tag = &caps[1];
+        let is_padded_counter = tag
+            .strip_prefix("counter:")
+            .is_some_and(|w| (1..=2).contains(&w.len()) && w.chars().all(|c| c.is_ascii_digit()));
+        if !NOTE_NAME_TAGS.contains(&tag) && !is_padded_counter {
+            valid = false;
+            warnings.push(format!("Unknown tag {{{}}} would appear literally in note names", tag));
+        }
+    }
+
+    let expanded = expand_note_name_template(&parse_counter_tag(&template).0);
+    if is_effectively_empty(&expanded) {
+        valid = false;
+        warnings.push("The name is empty, so notes would be called \"Untitled\"".to_string());
+    } else if expanded.trim().starts_with('.') {
+        valid = false;
+        warnings.push("Names starting with \".\" are hidden files and won't show up as notes".to_string());
+    }
+    if expanded.contains(['/', '\\']) {
+        warnings.push("Path separators are replaced with \"-\"; notes aren't put in subfolders".to_string());
+    }
+    if expanded.contains([':', '*', '?', '"', '<', '>', '|']) {
+        warnings.push("Characters not allowed in file names (: * ? \" < > |) are replaced with \"-\"".to_string());
+    }
 
-    Ok(preview)
+    NoteTemplateValidation {
+        valid,
+        expanded: note_name_preview(&template),
+        warnings,
+    }
 }
 
 // Preview mode: file content returned by read_file_direct / save_file_direct
@@ -1250,14 +6278,9 @@ async fn read_file_direct(path: String) -> Result<FileContent, String> {
         .await
         .map_err(|e| format!("Failed to read metadata: {}", e))?;
 
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+    let modified = mtime_secs(&metadata);
 
-    let title = extract_title(&content);
+    let title = extract_title(&content, TextLengths::default().title_fallback);
 
     Ok(FileContent {
         path,
@@ -1268,14 +6291,43 @@ async fn read_file_direct(path: String) -> Result<FileContent, String> {
 }
 
 #[tauri::command]
-async fn save_file_direct(path: String, content: String) -> Result<FileContent, String> {
+async fn save_file_direct(
+    path: String,
+    content: String,
+    allow_external: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<FileContent, String> {
     // For save, the file must already exist (we validate extension + path security)
     let canonical = validate_preview_path(&path)?;
 
+    // Files inside a read-only vault stay read-only when opened in a preview window
+    let notes_folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+    let in_vault = notes_folder
+        .and_then(|folder| PathBuf::from(folder).canonicalize().ok())
+        .is_some_and(|root| canonical.starts_with(root));
+    if in_vault {
+        ensure_writable(&state)?;
+    }
+
+    // Outside the vault, only files opened in a preview window may be written,
+    // unless the caller explicitly opts in
+    let is_preview_file = state
+        .preview_files
+        .lock()
+        .expect("preview files mutex")
+        .contains(&canonical);
+    if !in_vault && !is_preview_file && !allow_external.unwrap_or(false) {
+        return Err(format!("Not allowed to save outside the notes folder: {}", path));
+    }
+
     if !canonical.is_file() {
         return Err(format!("Not a file: {}", path));
     }
 
+    mark_self_write(&state, &canonical);
     fs::write(&canonical, &content)
         .await
         .map_err(|e| format!("Failed to write file: {}", e))?;
@@ -1283,14 +6335,9 @@ async fn save_file_direct(path: String, content: String) -> Result<FileContent,
     let metadata = fs::metadata(&canonical)
         .await
         .map_err(|e| format!("Failed to read metadata: {}", e))?;
-    let modified = metadata
-        .modified()
-        .ok()
-        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
+    let modified = mtime_secs(&metadata);
 
-    let title = extract_title(&content);
+    let title = extract_title(&content, TextLengths::default().title_fallback);
 
     Ok(FileContent {
         path,
@@ -1300,40 +6347,467 @@ async fn save_file_direct(path: String, content: String) -> Result<FileContent,
     })
 }
 
+/// Write one CSV row per note (id, title, modified, created, word_count, tag_count) to
+/// `out_path`. Timestamps are Unix seconds; `created` is empty where the filesystem
+/// doesn't record it. Returns the number of rows written.
+#[tauri::command]
+async fn export_metadata_csv(out_path: String, state: State<'_, AppState>) -> Result<usize, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let out = PathBuf::from(&out_path);
+    let is_csv = out
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    if !is_csv {
+        return Err("Export file must have a .csv extension".to_string());
+    }
+
+    let path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    tokio::task::spawn_blocking(move || {
+        let unix_secs = |t: std::io::Result<std::time::SystemTime>| {
+            t.ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs().to_string())
+        };
+
+        let mut rows: Vec<[String; 6]> = Vec::new();
+        for (id, entry) in walk_notes(&path, scan) {
+            let Ok(content) = read_note_file(entry.path()) else {
+                continue;
+            };
+            let metadata = entry.metadata().ok();
+            rows.push([
+                id,
+                extract_title(&content, title_fallback_length),
+                metadata
+                    .as_ref()
+                    .and_then(|m| unix_secs(m.modified()))
+                    .unwrap_or_else(|| "0".to_string()),
+                metadata
+                    .as_ref()
+                    .and_then(|m| unix_secs(m.created()))
+                    .unwrap_or_default(),
+                count_words(&content).to_string(),
+                extract_tags(&content).len().to_string(),
+            ]);
+        }
+        rows.sort_by(|a, b| a[0].cmp(&b[0]));
+
+        let mut writer = csv::Writer::from_path(&out)
+            .map_err(|e| format!("Failed to create export file: {}", e))?;
+        writer
+            .write_record(["id", "title", "modified", "created", "word_count", "tag_count"])
+            .map_err(|e| e.to_string())?;
+        for row in &rows {
+            writer.write_record(row).map_err(|e| e.to_string())?;
+        }
+        writer
+            .flush()
+            .map_err(|e| format!("Failed to write export file: {}", e))?;
+
+        Ok(rows.len())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Copy a note and the vault assets its images reference into `out_dir`, as
+/// `<name>.md` plus `assets/...`, with the note's image paths rewritten to point into
+/// the bundle. Returns the files written.
+#[tauri::command]
+async fn export_note_bundle(
+    id: String,
+    out_dir: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let folder_path = PathBuf::from(&folder);
+
+    let out = PathBuf::from(out_dir.trim());
+    if !out.is_absolute() {
+        return Err("Export folder must be an absolute path".to_string());
+    }
+    if out.is_file() {
+        return Err(format!("Not a folder: {}", out.display()));
+    }
+    // Exporting into the vault would turn the bundle into more notes and assets
+    let in_vault = match (out.canonicalize(), folder_path.canonicalize()) {
+        (Ok(out), Ok(root)) => out.starts_with(root),
+        _ => out.starts_with(&folder_path),
+    };
+    if in_vault {
+        return Err("Export folder can't be inside the notes folder".to_string());
+    }
+
+    let file_path = abs_path_from_id(&folder_path, &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    let leaf = id.rsplit('/').next().unwrap_or(&id);
+    let note_out = out.join(format!("{}.md", leaf));
+    if note_out.exists() {
+        return Err(format!("{} already exists", note_out.display()));
+    }
+
+    // Referenced files under the vault's assets/ folder, keyed by the src as written
+    let assets_root = folder_path.join("assets");
+    let mut bundled: HashMap<String, PathBuf> = HashMap::new();
+    for src in extract_image_refs(&content) {
+        if is_external_ref(&src) {
+            continue;
+        }
+        if let Ok(path) = resolve_note_relative_path(&folder_path, &id, &src) {
+            if path.starts_with(&assets_root) && path.is_file() {
+                bundled.insert(src, path);
+            }
+        }
+    }
+
+    fs::create_dir_all(&out)
+        .await
+        .map_err(|e| format!("Failed to create export folder: {}", e))?;
+
+    let mut written = Vec::new();
+    let mut copied: HashSet<PathBuf> = HashSet::new();
+    for path in bundled.values() {
+        if !copied.insert(path.clone()) {
+            continue;
+        }
+        let rel = path.strip_prefix(&folder_path).map_err(|e| e.to_string())?;
+        let dest = out.join(rel);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        fs::copy(path, &dest)
+            .await
+            .map_err(|e| format!("Failed to copy {}: {}", rel.display(), e))?;
+        written.push(dest.to_string_lossy().into_owned());
+    }
+
+    // Point image refs at the bundle's assets/ folder. Replace back to front so earlier
+    // spans stay valid.
+    let mut exported = content.clone();
+    for (span, src) in image_ref_spans(&content).into_iter().rev() {
+        let Some(path) = bundled.get(&src) else {
+            continue;
+        };
+        let rel = path
+            .strip_prefix(&folder_path)
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .map_err(|e| e.to_string())?;
+        let in_angle_brackets = content[span.clone()].starts_with('<');
+        let replacement = if in_angle_brackets {
+            format!("<{}>", rel)
+        } else {
+            links::encode_link_destination(&rel)
+        };
+        exported.replace_range(span, &replacement);
+    }
+
+    fs::write(&note_out, &exported)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", note_out.display(), e))?;
+    written.insert(0, note_out.to_string_lossy().into_owned());
+
+    Ok(written)
+}
+
+/// Notes whose ID starts with `prefix`, e.g. "work/projects/" for everything in that
+/// folder or "work/pro" while typing a path. A path filter over the note list cache, not
+/// a text search.
+#[tauri::command]
+fn find_notes_by_prefix(prefix: String, state: State<AppState>) -> Result<Vec<NoteMetadata>, String> {
+    let validated = validate_folder_id(&prefix)?;
+    let prefix = if prefix.trim().ends_with('/') {
+        format!("{}/", validated)
+    } else {
+        validated
+    };
+
+    let cache = state.notes_cache.read().expect("cache read lock");
+    let mut notes: Vec<NoteMetadata> = cache
+        .values()
+        .filter(|note| note.id.starts_with(&prefix))
+        .cloned()
+        .collect();
+    notes.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(notes)
+}
+
+/// Title autocomplete for "jump to note", separate from full-text search_notes. Without
+/// an index, falls back to titles in the note list cache that start with the query.
+#[tauri::command]
+fn search_prefix(
+    query: String,
+    limit: Option<usize>,
+    state: State<AppState>,
+) -> Result<Vec<PrefixMatch>, String> {
+    let query = query.trim();
+    let limit = limit.unwrap_or(SEARCH_LIMIT).max(1);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let title_fallback_length = vault_text_lengths(&state).title_fallback;
+    {
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            return search_index
+                .search_prefix(query, limit, title_fallback_length)
+                .map_err(|e| e.to_string());
+        }
+    }
+
+    let lower = query.to_lowercase();
+    let cache = state.notes_cache.read().expect("cache read lock");
+    let mut matches: Vec<PrefixMatch> = cache
+        .values()
+        .filter(|note| note.title.to_lowercase().starts_with(&lower))
+        .map(|note| PrefixMatch {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            score: 1.0,
+        })
+        .collect();
+    matches.sort_by(|a, b| a.title.cmp(&b.title));
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+/// Past search queries for the current vault, most recent first
+#[tauri::command]
+fn get_recent_searches(state: State<AppState>) -> Vec<String> {
+    let app_config = state.app_config.read().expect("app_config read lock");
+    app_config
+        .notes_folder
+        .as_deref()
+        .map(load_recent_searches)
+        .unwrap_or_default()
+}
+
+/// Remember a submitted search query. Called when the user commits to a search (e.g.
+/// presses Enter), not on every keystroke search_notes sees.
+#[tauri::command]
+fn add_recent_search(query: String, state: State<AppState>) -> Result<(), String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    record_recent_search(&folder, query).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn clear_recent_searches(state: State<AppState>) -> Result<(), String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let path = get_recent_searches_path(&folder);
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Search notes, optionally scoped to a subfolder (e.g. "work"). Multi-word queries
+/// match any term by default; `match_all` requires every term to match.
+///
+/// Folder scoping is a post-filter on the ranked Tantivy results: we fetch a larger
+/// candidate set, drop notes outside the folder, then apply the usual limit. A folder
+/// whose matches all rank below the candidate window can therefore come back short.
 #[tauri::command]
-async fn search_notes(query: String, state: State<'_, AppState>) -> Result<Vec<SearchResult>, String> {
+async fn search_notes(
+    query: String,
+    folder: Option<String>,
+    match_all: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<SearchResponse, String> {
     let trimmed_query = query.trim().to_string();
     if trimmed_query.is_empty() {
-        return Ok(vec![]);
+        return Ok(SearchResponse {
+            results: vec![],
+            degraded: false,
+        });
     }
 
+    let folder_prefix = folder
+        .as_deref()
+        .map(validate_folder_id)
+        .transpose()?
+        .map(|f| format!("{}/", f));
+
+    let candidate_limit = if folder_prefix.is_some() { SCOPED_SEARCH_CANDIDATES } else { SEARCH_LIMIT };
+
     // Check if search index is available and use it (scoped to drop lock before await)
+    let lengths = vault_text_lengths(&state);
     let indexed_result = {
         let index = state.search_index.lock().expect("search index mutex");
         (*index).as_ref().map(|search_index| {
-            search_index.search(&trimmed_query, 20).map_err(|e| e.to_string())
+            search_index
+                .search(
+                    &trimmed_query,
+                    candidate_limit,
+                    match_all.unwrap_or(false),
+                    lengths,
+                )
+                .map_err(|e| e.to_string())
         })
     };
 
-    match indexed_result {
-        Some(Ok(results)) if !results.is_empty() => Ok(results),
+    let indexed_result = indexed_result.map(|result| {
+        result.map(|mut results| {
+            if let Some(ref prefix) = folder_prefix {
+                results.retain(|r| r.id.starts_with(prefix.as_str()));
+                results.truncate(SEARCH_LIMIT);
+            }
+            results
+        })
+    });
+
+    let (results, degraded) = match indexed_result {
+        Some(Ok(results)) if !results.is_empty() => (results, false),
         Some(Ok(_)) => {
             // Tantivy can miss partial/fuzzy matches; fall back to substring search.
-            fallback_search(&trimmed_query, &state).await
+            (fallback_search(&trimmed_query, folder_prefix.as_deref(), &state).await?, false)
         }
         Some(Err(e)) => {
             eprintln!("Tantivy search error, falling back to substring search: {}", e);
-            fallback_search(&trimmed_query, &state).await
+            (fallback_search(&trimmed_query, folder_prefix.as_deref(), &state).await?, true)
         }
         None => {
             // Fallback to simple search if index not available
-            fallback_search(&trimmed_query, &state).await
+            (fallback_search(&trimmed_query, folder_prefix.as_deref(), &state).await?, true)
+        }
+    };
+
+    Ok(SearchResponse { results, degraded })
+}
+
+// Scale one vault's BM25 scores so its best hit scores 1.0. Raw scores depend on each
+// index's term statistics, so they can't be compared across vaults as they are.
+fn normalize_vault_scores(results: &mut [SearchResult]) {
+    let top = results.iter().map(|r| r.score).fold(0.0_f32, f32::max);
+    if top > 0.0 {
+        for result in results.iter_mut() {
+            result.score /= top;
+        }
+    }
+}
+
+/// Search the open vault and every recent vault that has an index on disk. Other
+/// vaults' indexes are opened read-only for the query; a vault that has never been
+/// opened with this version has no index yet and is skipped.
+#[tauri::command]
+async fn search_all_vaults(
+    app: AppHandle,
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchResult>, String> {
+    let trimmed_query = query.trim().to_string();
+    if trimmed_query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let (active, recent) = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        (
+            app_config.notes_folder.clone(),
+            app_config.recent_vaults.clone().unwrap_or_default(),
+        )
+    };
+
+    let mut results: Vec<SearchResult> = Vec::new();
+
+    if let Some(ref active) = active {
+        let lengths = vault_text_lengths(&state);
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            if let Ok(mut found) = search_index.search(&trimmed_query, SEARCH_LIMIT, false, lengths)
+            {
+                normalize_vault_scores(&mut found);
+                results.extend(found.into_iter().map(|r| SearchResult {
+                    vault: Some(active.clone()),
+                    ..r
+                }));
+            }
         }
     }
+
+    let index_root = get_search_index_root(&app).map_err(|e| e.to_string())?;
+    let others: Vec<String> = recent
+        .into_iter()
+        .filter(|vault| Some(vault) != active.as_ref() && Path::new(vault).is_dir())
+        .collect();
+    let found = tokio::task::spawn_blocking(move || {
+        let mut found: Vec<SearchResult> = Vec::new();
+        for vault in others {
+            let vault_settings = load_settings(&vault);
+            let search_index = match SearchIndex::open_read_only(
+                &vault_search_index_path(&index_root, &vault),
+                search_text_analyzer(&vault_settings),
+                index_source_root(&vault_settings, &vault),
+            ) {
+                Ok(index) => index,
+                Err(_) => continue,
+            };
+            let lengths = text_lengths(&vault_settings);
+            if let Ok(mut vault_results) =
+                search_index.search(&trimmed_query, SEARCH_LIMIT, false, lengths)
+            {
+                normalize_vault_scores(&mut vault_results);
+                found.extend(vault_results.into_iter().map(|r| SearchResult {
+                    vault: Some(vault.clone()),
+                    ..r
+                }));
+            }
+        }
+        found
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    results.extend(found);
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(SEARCH_LIMIT);
+    Ok(results)
 }
 
-// Fallback search when Tantivy index isn't available - searches title and full content
-async fn fallback_search(query: &str, state: &State<'_, AppState>) -> Result<Vec<SearchResult>, String> {
+// Fallback search when Tantivy index isn't available - searches title and full content.
+// `folder_prefix` (e.g. "work/") restricts the scan to notes under that folder.
+async fn fallback_search(
+    query: &str,
+    folder_prefix: Option<&str>,
+    state: &State<'_, AppState>,
+) -> Result<Vec<SearchResult>, String> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
@@ -1349,6 +6823,7 @@ async fn fallback_search(query: &str, state: &State<'_, AppState>) -> Result<Vec
         let cache = state.notes_cache.read().expect("cache read lock");
         cache
             .values()
+            .filter(|note| folder_prefix.is_none_or(|prefix| note.id.starts_with(prefix)))
             .map(|note| {
                 (
                     note.id.clone(),
@@ -1377,7 +6852,7 @@ async fn fallback_search(query: &str, state: &State<'_, AppState>) -> Result<Vec
             Ok(p) => p,
             Err(_) => continue,
         };
-        if let Ok(content) = tokio::fs::read_to_string(&file_path).await {
+        if let Ok(content) = read_note_text(&file_path).await.map(|(content, _)| content) {
             let content_lower = content.to_lowercase();
             if content_lower.contains(&query_lower) {
                 // Higher score if in title, lower if only in content
@@ -1396,17 +6871,19 @@ async fn fallback_search(query: &str, state: &State<'_, AppState>) -> Result<Vec
                 preview,
                 modified,
                 score,
+                vault: None,
             });
         }
     }
 
     results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-    results.truncate(20);
+    results.truncate(SEARCH_LIMIT);
 
     Ok(results)
 }
 
-// File watcher event payload
+// Per-change event emitted by commands that rename or move notes (the watcher sends
+// batched files-changed events instead). For "renamed" events, changed_ids is [old_id, new_id].
 #[derive(Clone, Serialize)]
 struct FileChangeEvent {
     kind: String,
@@ -1414,24 +6891,340 @@ struct FileChangeEvent {
     changed_ids: Vec<String>,
 }
 
+// Bounds for the watcher's frontend notification window (watcherDebounceMs)
+const DEFAULT_WATCHER_DEBOUNCE_MS: u64 = 300;
+const MIN_WATCHER_DEBOUNCE_MS: u64 = 50;
+const MAX_WATCHER_DEBOUNCE_MS: u64 = 5000;
+
+// Batched watcher notification, sent once per debounce window. `renamed` holds
+// [old_id, new_id] pairs; both IDs also appear in changed_ids.
+#[derive(Clone, Serialize, Default)]
+struct FilesChangedEvent {
+    changed_ids: Vec<String>,
+    renamed: Vec<(String, String)>,
+}
+
+// Collects watcher changes and emits them as one files-changed event after the window.
+// Index updates happen immediately; only the frontend notification waits.
+struct WatcherNotifier {
+    app: AppHandle,
+    window: Duration,
+    pending: Mutex<(FilesChangedEvent, bool)>, // (batch, flush scheduled)
+}
+
+impl WatcherNotifier {
+    fn queue(self: &Arc<Self>, ids: &[&str], renamed: Option<(String, String)>) {
+        let mut pending = self.pending.lock().expect("watcher batch mutex");
+        let (batch, scheduled) = &mut *pending;
+        for id in ids {
+            if !batch.changed_ids.iter().any(|existing| existing == id) {
+                batch.changed_ids.push(id.to_string());
+            }
+        }
+        if let Some(pair) = renamed {
+            batch.renamed.push(pair);
+        }
+
+        if !*scheduled {
+            *scheduled = true;
+            let notifier = Arc::clone(self);
+            std::thread::spawn(move || {
+                std::thread::sleep(notifier.window);
+                let batch = {
+                    let mut pending = notifier.pending.lock().expect("watcher batch mutex");
+                    pending.1 = false;
+                    std::mem::take(&mut pending.0)
+                };
+                if !batch.changed_ids.is_empty() {
+                    let _ = notifier.app.emit("files-changed", batch);
+                }
+            });
+        }
+    }
+}
+
+// Watcher events for a path the app itself wrote within this window are echoes
+// of that write and are ignored
+const SELF_WRITE_WINDOW: Duration = Duration::from_secs(2);
+
+/// Record that the app is about to write (or remove) `path`, so the watcher skips the echo
+fn mark_self_write(state: &AppState, path: &Path) {
+    let mut self_writes = state.self_writes.lock().expect("self writes mutex");
+    let now = Instant::now();
+    self_writes.retain(|_, at| now.duration_since(*at) < SELF_WRITE_WINDOW);
+    self_writes.insert(path.to_path_buf(), now);
+}
+
+fn is_self_write(app_handle: &AppHandle, path: &Path) -> bool {
+    app_handle.try_state::<AppState>().is_some_and(|state| {
+        state
+            .self_writes
+            .lock()
+            .expect("self writes mutex")
+            .get(path)
+            .is_some_and(|at| at.elapsed() < SELF_WRITE_WINDOW)
+    })
+}
+
+// Tell the frontend `.scratch/settings.json` changed on disk, unless it now matches the
+// settings in memory (the app's own saves land here too)
+fn notify_settings_changed(app_handle: &AppHandle, notes_folder: &str) {
+    let Some(state) = app_handle.try_state::<AppState>() else {
+        return;
+    };
+    let on_disk = serde_json::to_value(load_settings(notes_folder)).ok();
+    let in_memory = serde_json::to_value(&*state.settings.read().expect("settings read lock")).ok();
+    if on_disk != in_memory {
+        let _ = app_handle.emit("settings-changed", ());
+    }
+}
+
+// How long a rename's "from" half waits for its "to" half before being treated as a
+// move out of the vault (a delete)
+const RENAME_PAIR_WINDOW: Duration = Duration::from_millis(200);
+
+/// Update the search index for an external change to one note and notify the frontend.
+/// `kind` is "created", "modified" or "deleted".
+fn apply_watcher_change(
+    app_handle: &AppHandle,
+    notifier: &Arc<WatcherNotifier>,
+    path: &Path,
+    note_id: &str,
+    kind: &str,
+) {
+    // Update search index for external file changes
+    if let Some(state) = app_handle.try_state::<AppState>() {
+        let title_fallback_length = vault_text_lengths(&state).title_fallback;
+        let index = state.search_index.lock().expect("search index mutex");
+        if let Some(ref search_index) = *index {
+            match kind {
+                "created" | "modified" => {
+                    match read_note_file(path) {
+                        Ok(content) => {
+                            let title = extract_title(&content, title_fallback_length);
+                            let modified = std::fs::metadata(path)
+                                .ok()
+                                .map(|m| mtime_secs(&m))
+                                .unwrap_or(0);
+                            let _ = search_index.index_note(note_id, &title, &content, modified);
+                        }
+                        Err(_) => {
+                            // File gone between event and read — treat as deletion
+                            if !path.exists() {
+                                let _ = search_index.delete_note(note_id);
+                            }
+                        }
+                    }
+                }
+                "deleted" => {
+                    let _ = search_index.delete_note(note_id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    notifier.queue(&[note_id], None);
+}
+
+/// Apply a rename seen by the watcher as one operation: the old ID leaves the index,
+/// the new one is added, and the frontend gets a single "renamed" event. Renames
+/// between a note and a non-note (e.g. an editor's temp file) become a plain
+/// create/modify or delete of the note side.
+fn apply_watcher_rename(
+    app_handle: &AppHandle,
+    notifier: &Arc<WatcherNotifier>,
+    notes_root: &Path,
+    from: &Path,
+    to: &Path,
+) {
+    match (id_from_abs_path(notes_root, from), id_from_abs_path(notes_root, to)) {
+        (Some(old_id), Some(new_id)) => {
+            if let Some(state) = app_handle.try_state::<AppState>() {
+                let title_fallback_length = vault_text_lengths(&state).title_fallback;
+                let index = state.search_index.lock().expect("search index mutex");
+                if let Some(ref search_index) = *index {
+                    let _ = search_index.delete_note(&old_id);
+                    if let Ok(content) = read_note_file(to) {
+                        let modified = std::fs::metadata(to)
+                            .ok()
+                            .map(|m| mtime_secs(&m))
+                            .unwrap_or(0);
+                        let _ = search_index.index_note(
+                            &new_id,
+                            &extract_title(&content, title_fallback_length),
+                            &content,
+                            modified,
+                        );
+                    }
+                }
+            }
+
+            notifier.queue(&[&old_id, &new_id], Some((old_id.clone(), new_id.clone())));
+        }
+        (None, Some(new_id)) => apply_watcher_change(app_handle, notifier, to, &new_id, "modified"),
+        (Some(old_id), None) => apply_watcher_change(app_handle, notifier, from, &old_id, "deleted"),
+        (None, None) => {}
+    }
+}
+
+// The "from" half of a rename seen by the watcher
+struct PendingRename {
+    at: Instant,
+    // Rename cookie on backends that pair the halves themselves (inotify)
+    tracker: Option<usize>,
+}
+
+// Remove and return the "from" path that a rename's "to" half belongs to: the one with
+// the same tracker where the backend gives one, else the latest still in the window
+fn take_rename_source(
+    pending: &Mutex<HashMap<PathBuf, PendingRename>>,
+    tracker: Option<usize>,
+) -> Option<PathBuf> {
+    let mut pending = pending.lock().expect("pending rename mutex");
+    let from = pending
+        .iter()
+        .filter(|(_, p)| p.at.elapsed() < RENAME_PAIR_WINDOW)
+        .filter(|(_, p)| tracker.is_none() || p.tracker == tracker)
+        .max_by_key(|(_, p)| p.at)
+        .map(|(path, _)| path.clone())?;
+    pending.remove(&from);
+    Some(from)
+}
+
+// One thread per watcher that treats renames whose "to" half never came as moves out of
+// the vault
+fn spawn_rename_sweeper(
+    pending: std::sync::Weak<Mutex<HashMap<PathBuf, PendingRename>>>,
+    app_handle: AppHandle,
+    notifier: Arc<WatcherNotifier>,
+    notes_root: PathBuf,
+) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(RENAME_PAIR_WINDOW / 2);
+        let Some(pending) = pending.upgrade() else {
+            return;
+        };
+        let mut unpaired = Vec::new();
+        pending.lock().expect("pending rename mutex").retain(|path, p| {
+            let waiting = p.at.elapsed() < RENAME_PAIR_WINDOW;
+            if !waiting {
+                unpaired.push(path.clone());
+            }
+            waiting
+        });
+        drop(pending);
+
+        for path in unpaired {
+            if let Some(note_id) = id_from_abs_path(&notes_root, &path) {
+                apply_watcher_change(&app_handle, &notifier, &path, &note_id, "deleted");
+            }
+        }
+    });
+}
+
 fn setup_file_watcher(
     app: AppHandle,
     notes_folder: &str,
     debounce_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
 ) -> Result<FileWatcherState, String> {
+    use notify::event::{ModifyKind, RenameMode};
+
     let folder_path = PathBuf::from(notes_folder);
     let notes_root = folder_path.clone();
+    let settings_path = get_settings_path(notes_folder);
+    let folder_str = notes_folder.to_string();
     let app_handle = app.clone();
+    let window_ms = app
+        .try_state::<AppState>()
+        .and_then(|state| state.settings.read().expect("settings read lock").watcher_debounce_ms)
+        .unwrap_or(DEFAULT_WATCHER_DEBOUNCE_MS)
+        .clamp(MIN_WATCHER_DEBOUNCE_MS, MAX_WATCHER_DEBOUNCE_MS);
+    let notifier = Arc::new(WatcherNotifier {
+        app: app.clone(),
+        window: Duration::from_millis(window_ms),
+        pending: Mutex::new((FilesChangedEvent::default(), false)),
+    });
+    // "From" halves of renames waiting for their "to" half. The sweeper turns the ones
+    // left unpaired into deletes, and stops once the watcher (and so this map) is dropped.
+    let pending_renames: Arc<Mutex<HashMap<PathBuf, PendingRename>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    spawn_rename_sweeper(
+        Arc::downgrade(&pending_renames),
+        app.clone(),
+        Arc::clone(&notifier),
+        notes_root.clone(),
+    );
 
     let watcher = RecommendedWatcher::new(
         move |res: Result<notify::Event, notify::Error>| {
             if let Ok(event) = res {
+                // Renames. inotify sends From, To, then Both with the two paths; Windows
+                // sends From and To; FSEvents sends Any for each side. Pair each "to" with
+                // a recent "from", and treat an unpaired "from" as a move out of the vault.
+                if let notify::EventKind::Modify(ModifyKind::Name(mode)) = event.kind {
+                    if mode == RenameMode::Both {
+                        // Already handled when the To half arrived
+                        return;
+                    }
+                    for path in event.paths.iter() {
+                        if is_self_write(&app_handle, path) {
+                            continue;
+                        }
+
+                        let is_from = match mode {
+                            RenameMode::From => true,
+                            RenameMode::To => false,
+                            _ => !path.exists(),
+                        };
+
+                        // Keep the usual event path from reacting to the same change
+                        debounce_map
+                            .lock()
+                            .expect("debounce map mutex")
+                            .insert(path.clone(), Instant::now());
+
+                        if is_from {
+                            pending_renames.lock().expect("pending rename mutex").insert(
+                                path.clone(),
+                                PendingRename {
+                                    at: Instant::now(),
+                                    tracker: event.attrs.tracker(),
+                                },
+                            );
+                        } else {
+                            let from = take_rename_source(&pending_renames, event.attrs.tracker());
+                            match from {
+                                Some(from) => {
+                                    apply_watcher_rename(&app_handle, &notifier, &notes_root, &from, path)
+                                }
+                                None => {
+                                    // Moved in from outside the vault
+                                    if let Some(note_id) = id_from_abs_path(&notes_root, path) {
+                                        apply_watcher_change(&app_handle, &notifier, path, &note_id, "created");
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    return;
+                }
+
                 for path in event.paths.iter() {
+                    if *path == settings_path {
+                        notify_settings_changed(&app_handle, &folder_str);
+                        continue;
+                    }
+
                     let note_id = match id_from_abs_path(&notes_root, path) {
                         Some(id) => id,
                         None => continue,
                     };
 
+                    if is_self_write(&app_handle, path) {
+                        continue;
+                    }
+
                     // Debounce with cleanup
                     {
                         let mut map = debounce_map.lock().expect("debounce map mutex");
@@ -1453,60 +7246,12 @@ fn setup_file_watcher(
                         notify::EventKind::Create(_) => "created",
                         notify::EventKind::Modify(_) => "modified",
                         notify::EventKind::Remove(_) => "deleted",
-                        // Some backends emit Any for renames or unclassified changes
+                        // Some backends emit Any for unclassified changes
                         notify::EventKind::Any => "modified",
                         _ => continue,
                     };
 
-                    // Update search index for external file changes
-                    if let Some(state) = app_handle.try_state::<AppState>() {
-                        let index = state.search_index.lock().expect("search index mutex");
-                        if let Some(ref search_index) = *index {
-                            match kind {
-                                "created" | "modified" => {
-                                    match std::fs::read_to_string(path) {
-                                        Ok(content) => {
-                                            let title = extract_title(&content);
-                                            let modified = std::fs::metadata(path)
-                                                .ok()
-                                                .and_then(|m| m.modified().ok())
-                                                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                                                .map(|d| d.as_secs() as i64)
-                                                .unwrap_or(0);
-                                            let _ = search_index.index_note(&note_id, &title, &content, modified);
-                                        }
-                                        Err(_) => {
-                                            // File gone between event and read — treat as deletion
-                                            if !path.exists() {
-                                                let _ = search_index.delete_note(&note_id);
-                                            }
-                                        }
-                                    }
-                                }
-                                "deleted" => {
-                                    let _ = search_index.delete_note(&note_id);
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-
-                    // Determine the actual kind for the frontend event
-                    // (a "modified" event on a non-existent file is really a delete)
-                    let effective_kind = if kind == "modified" && !path.exists() {
-                        "deleted"
-                    } else {
-                        kind
-                    };
-
-                    let _ = app_handle.emit(
-                        "file-change",
-                        FileChangeEvent {
-                            kind: effective_kind.to_string(),
-                            path: path.to_string_lossy().into_owned(),
-                            changed_ids: vec![note_id.clone()],
-                        },
-                    );
+                    apply_watcher_change(&app_handle, &notifier, path, &note_id, kind);
                 }
             }
         },
@@ -1521,7 +7266,261 @@ fn setup_file_watcher(
         .watch(&folder_path, RecursiveMode::Recursive)
         .map_err(|e| e.to_string())?;
 
-    Ok(FileWatcherState { watcher })
+    Ok(FileWatcherState { watcher })
+}
+
+// Event sent to a preview window when its file changes on disk
+#[derive(Clone, Serialize)]
+struct PreviewFileChangeEvent {
+    kind: String,
+    path: String,
+}
+
+/// Watch the file shown in the calling preview window and send that window a
+/// `preview-file-change` event when it's changed elsewhere. The watcher is dropped when
+/// the window closes. Only files opened in a preview window can be watched.
+#[tauri::command]
+fn watch_external_file(
+    app: AppHandle,
+    window: tauri::WebviewWindow,
+    path: String,
+    state: State<AppState>,
+) -> Result<(), String> {
+    let canonical = validate_preview_path(&path)?;
+    let is_preview_file = state
+        .preview_files
+        .lock()
+        .expect("preview files mutex")
+        .contains(&canonical);
+    if !is_preview_file {
+        return Err(format!("Not a file opened in a preview window: {}", path));
+    }
+
+    // Watch the parent folder: editors often save by replacing the file, which would
+    // orphan a watch on the file itself
+    let parent = canonical
+        .parent()
+        .ok_or_else(|| format!("Cannot watch {}", path))?
+        .to_path_buf();
+    let label = window.label().to_string();
+    let app_handle = app.clone();
+    let target = canonical.clone();
+    let last_emit: Mutex<Option<Instant>> = Mutex::new(None);
+
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| {
+            let Ok(event) = res else { return };
+            if !event.paths.iter().any(|p| p.file_name() == target.file_name()) {
+                return;
+            }
+            // The preview's own saves come back through save_file_direct
+            if is_self_write(&app_handle, &target) {
+                return;
+            }
+            let kind = match event.kind {
+                notify::EventKind::Remove(_) if !target.exists() => "deleted",
+                notify::EventKind::Create(_)
+                | notify::EventKind::Modify(_)
+                | notify::EventKind::Remove(_)
+                | notify::EventKind::Any => "modified",
+                _ => return,
+            };
+            {
+                let mut last = last_emit.lock().expect("preview watcher mutex");
+                let now = Instant::now();
+                if kind == "modified"
+                    && last.is_some_and(|at| now.duration_since(at) < Duration::from_millis(500))
+                {
+                    return;
+                }
+                *last = Some(now);
+            }
+            let _ = app_handle.emit_to(
+                label.as_str(),
+                "preview-file-change",
+                PreviewFileChangeEvent {
+                    kind: kind.to_string(),
+                    path: target.to_string_lossy().into_owned(),
+                },
+            );
+        },
+        Config::default(),
+    )
+    .map_err(|e| e.to_string())?;
+    watcher
+        .watch(&parent, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    // Replaces any earlier watcher for this window
+    state
+        .preview_watchers
+        .lock()
+        .expect("preview watchers mutex")
+        .insert(window.label().to_string(), FileWatcherState { watcher });
+    Ok(())
+}
+
+/// Best-effort commit of the search index on exit. Runs on its own thread and is given
+/// INDEX_FLUSH_TIMEOUT, so a stuck writer can't keep the app from quitting.
+fn flush_search_index(app: &AppHandle) {
+    let app = app.clone();
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let state = app.state::<AppState>();
+        if let Ok(index) = state.search_index.lock() {
+            if let Some(ref search_index) = *index {
+                if let Err(e) = search_index.flush() {
+                    eprintln!("Failed to commit search index on exit: {}", e);
+                }
+            }
+        }
+        let _ = done_tx.send(());
+    });
+    if done_rx.recv_timeout(INDEX_FLUSH_TIMEOUT).is_err() {
+        eprintln!("Search index commit did not finish before exit");
+    }
+}
+
+/// Whether another app instance has the current vault open. The vault can be taken over
+/// by calling set_notes_folder with `force_takeover`.
+#[tauri::command]
+fn vault_lock_status(state: State<AppState>) -> VaultLockStatus {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+    let holder = folder.as_deref().and_then(read_vault_lock);
+    VaultLockStatus {
+        held_by_other: holder.as_ref().is_some_and(lock_held_by_other),
+        holder,
+    }
+}
+
+/// Whether the configured notes folder currently exists and is writable. Meant to be
+/// polled while the vault is unavailable: the watcher is dropped when the folder
+/// disappears, and the watcher and search index are re-initialized when it comes back.
+#[tauri::command]
+async fn vault_available(app: AppHandle, state: State<'_, AppState>) -> Result<bool, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        match app_config.notes_folder.clone() {
+            Some(folder) => folder,
+            None => return Ok(false),
+        }
+    };
+
+    let path = PathBuf::from(&folder);
+    let available = std::fs::metadata(&path)
+        .map(|m| m.is_dir() && !m.permissions().readonly())
+        .unwrap_or(false);
+
+    if !available {
+        // A watcher on a vanished mount is dead; drop it so reappearance re-inits
+        state.file_watcher.lock().expect("file watcher mutex").take();
+        return Ok(false);
+    }
+
+    let needs_reinit = state.file_watcher.lock().expect("file watcher mutex").is_none();
+    if needs_reinit {
+        reinit_vault_watch(app, state, &folder).await?;
+    }
+
+    Ok(true)
+}
+
+// Start a fresh watcher on the vault and rebuild the cache and search index from disk
+async fn reinit_vault_watch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    folder: &str,
+) -> Result<(), String> {
+    cleanup_debounce_map(&state.debounce_map);
+    let watcher_state = setup_file_watcher(
+        app.clone(),
+        folder,
+        Arc::clone(&state.debounce_map),
+    )?;
+    *state.file_watcher.lock().expect("file watcher mutex") = Some(watcher_state);
+
+    state.notes_cache.write().expect("cache write lock").clear();
+    rebuild_search_index(app, state).await
+}
+
+// Result of revalidate_vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultRevalidation {
+    pub notes_folder: Option<String>,
+    pub available: bool,
+}
+
+/// Re-run the startup checks on the saved notes folder: normalize legacy paths, forget
+/// invalid ones, and if the folder is present (e.g. a drive was remounted) reload its
+/// settings and restart the watcher and search index. Backs a "reconnect" button.
+#[tauri::command]
+async fn revalidate_vault(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<VaultRevalidation, String> {
+    let saved = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+    let Some(saved) = saved else {
+        return Ok(VaultRevalidation {
+            notes_folder: None,
+            available: false,
+        });
+    };
+
+    let folder = match normalize_notes_folder_path(&saved) {
+        Ok(normalized) => {
+            let normalized = normalized.to_string_lossy().into_owned();
+            if normalized != saved && Path::new(&normalized).is_dir() {
+                let mut app_config = state.app_config.write().expect("app_config write lock");
+                app_config.notes_folder = Some(normalized.clone());
+                save_app_config(&app, &app_config).map_err(|e| e.to_string())?;
+                normalized
+            } else {
+                saved
+            }
+        }
+        Err(_) => {
+            let mut app_config = state.app_config.write().expect("app_config write lock");
+            app_config.notes_folder = None;
+            save_app_config(&app, &app_config).map_err(|e| e.to_string())?;
+            return Ok(VaultRevalidation {
+                notes_folder: None,
+                available: false,
+            });
+        }
+    };
+
+    let available = std::fs::metadata(&folder)
+        .map(|m| m.is_dir() && !m.permissions().readonly())
+        .unwrap_or(false);
+    if !available {
+        state.file_watcher.lock().expect("file watcher mutex").take();
+        return Ok(VaultRevalidation {
+            notes_folder: Some(folder),
+            available: false,
+        });
+    }
+
+    // Don't restart the watcher and index over a vault another instance has claimed
+    // meanwhile; the UI can take it over through set_notes_folder
+    acquire_vault_lock(&folder, false)?;
+
+    // Settings fall back to defaults while the folder is missing
+    let settings = load_settings(&folder);
+    *state.settings.write().expect("settings write lock") = settings;
+
+    reinit_vault_watch(app, state, &folder).await?;
+
+    Ok(VaultRevalidation {
+        notes_folder: Some(folder),
+        available: true,
+    })
 }
 
 #[tauri::command]
@@ -1559,6 +7558,8 @@ async fn save_clipboard_image(
     base64_data: String,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
+    ensure_writable(&state)?;
+
     // Guard against empty clipboard payload
     if base64_data.trim().is_empty() {
         return Err("Clipboard data is empty".to_string());
@@ -1618,6 +7619,8 @@ async fn copy_image_to_assets(
     source_path: String,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
+    ensure_writable(&state)?;
+
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
@@ -1673,7 +7676,7 @@ async fn copy_image_to_assets(
 }
 
 #[tauri::command]
-fn rebuild_search_index(app: AppHandle, state: State<AppState>) -> Result<(), String> {
+async fn rebuild_search_index(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
     let folder = {
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config
@@ -1682,13 +7685,40 @@ fn rebuild_search_index(app: AppHandle, state: State<AppState>) -> Result<(), St
             .ok_or("Notes folder not set")?
     };
 
-    let index_path = get_search_index_path(&app).map_err(|e| e.to_string())?;
+    let index_path = get_search_index_path(&app, &folder).map_err(|e| e.to_string())?;
+    let (writer_buffer_bytes, analyzer, scan, title_fallback_length, source_root) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            index_writer_buffer_bytes(&settings),
+            search_text_analyzer(&settings),
+            note_scan(&settings),
+            text_lengths(&settings).title_fallback,
+            index_source_root(&settings, &folder),
+        )
+    };
 
-    // Create new index
-    let search_index = SearchIndex::new(&index_path).map_err(|e| e.to_string())?;
-    search_index
-        .rebuild_index(&PathBuf::from(&folder))
-        .map_err(|e| e.to_string())?;
+    // Drop the current index first: its writer holds the directory lock, and the
+    // new index picks up any change to the buffer size or search language settings
+    state.search_index.lock().expect("search index mutex").take();
+
+    // Create new index on a blocking thread so progress events aren't held back
+    let app_clone = app.clone();
+    let search_index = tokio::task::spawn_blocking(move || -> Result<SearchIndex, String> {
+        let search_index =
+            SearchIndex::new(&index_path, writer_buffer_bytes, analyzer, source_root)
+                .map_err(|e| e.to_string())?;
+        search_index
+            .rebuild_index(
+                &PathBuf::from(&folder),
+                scan,
+                title_fallback_length,
+                Some(&app_clone),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(search_index)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
 
     let mut index = state.search_index.lock().expect("search index mutex");
     *index = Some(search_index);
@@ -1696,6 +7726,111 @@ fn rebuild_search_index(app: AppHandle, state: State<AppState>) -> Result<(), St
     Ok(())
 }
 
+/// Compare the notes on disk with the documents in the search index
+#[tauri::command]
+async fn verify_index(state: State<'_, AppState>) -> Result<IndexVerification, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let path = PathBuf::from(&folder);
+    let scan = vault_note_scan(&state);
+    let disk_ids: HashSet<String> = tokio::task::spawn_blocking(move || {
+        walk_notes(&path, scan).map(|(id, _)| id).collect()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let index_ids = {
+        let index = state.search_index.lock().expect("search index mutex");
+        let search_index = index.as_ref().ok_or("Search index not initialized")?;
+        search_index.indexed_ids().map_err(|e| e.to_string())?
+    };
+
+    let mut missing_from_index: Vec<String> = disk_ids.difference(&index_ids).cloned().collect();
+    let mut stale_in_index: Vec<String> = index_ids.difference(&disk_ids).cloned().collect();
+    missing_from_index.sort();
+    stale_in_index.sort();
+
+    Ok(IndexVerification {
+        in_sync: missing_from_index.is_empty() && stale_in_index.is_empty(),
+        missing_from_index,
+        stale_in_index,
+    })
+}
+
+/// Make the search reader catch up with committed index changes (e.g. made by another
+/// process) without rebuilding. Returns the document count afterwards.
+#[tauri::command]
+fn reload_search_reader(state: State<AppState>) -> Result<u64, String> {
+    let index = state.search_index.lock().expect("search index mutex");
+    let search_index = index.as_ref().ok_or("Search index not initialized")?;
+    search_index.reload_reader().map_err(|e| e.to_string())
+}
+
+/// Wipe the on-disk index and rebuild it from scratch. Returns true if the existing
+/// index was corrupt (present but unopenable), e.g. after an interrupted write.
+#[tauri::command]
+async fn repair_search_index(app: AppHandle, state: State<'_, AppState>) -> Result<bool, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let index_path = get_search_index_path(&app, &folder).map_err(|e| e.to_string())?;
+    let (writer_buffer_bytes, analyzer, scan, title_fallback_length, source_root) = {
+        let settings = state.settings.read().expect("settings read lock");
+        (
+            index_writer_buffer_bytes(&settings),
+            search_text_analyzer(&settings),
+            note_scan(&settings),
+            text_lengths(&settings).title_fallback,
+            index_source_root(&settings, &folder),
+        )
+    };
+
+    state.search_index.lock().expect("search index mutex").take();
+
+    let app_clone = app.clone();
+    let (search_index, was_corrupt) =
+        tokio::task::spawn_blocking(move || -> Result<(SearchIndex, bool), String> {
+            let was_corrupt = index_path.join("meta.json").exists()
+                && Index::open_in_dir(&index_path).is_err();
+
+            if index_path.exists() {
+                std::fs::remove_dir_all(&index_path)
+                    .map_err(|e| format!("Failed to remove search index: {}", e))?;
+            }
+
+            let search_index =
+                SearchIndex::new(&index_path, writer_buffer_bytes, analyzer, source_root)
+                    .map_err(|e| e.to_string())?;
+            search_index
+                .rebuild_index(
+                    &PathBuf::from(&folder),
+                    scan,
+                    title_fallback_length,
+                    Some(&app_clone),
+                )
+                .map_err(|e| e.to_string())?;
+            Ok((search_index, was_corrupt))
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let mut index = state.search_index.lock().expect("search index mutex");
+    *index = Some(search_index);
+
+    Ok(was_corrupt)
+}
+
 // UI helper commands - wrap Tauri plugins for consistent invoke-based API
 
 #[tauri::command]
@@ -1761,19 +7896,42 @@ async fn open_in_file_manager(path: String) -> Result<(), String> {
     Ok(())
 }
 
+// Schemes open_url_safe always allows, and schemes it never opens regardless of settings
+const BUILTIN_URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+const BLOCKED_URL_SCHEMES: &[&str] = &["file", "javascript", "vbscript", "data"];
+
 #[tauri::command]
-async fn open_url_safe(url: String) -> Result<(), String> {
-    // Validate URL scheme - only allow http, https, mailto
+async fn open_url_safe(url: String, state: State<'_, AppState>) -> Result<(), String> {
+    // Validate URL scheme - built-in safe schemes plus any allowed in settings
     let parsed = url::Url::parse(&url).map_err(|e| format!("Invalid URL: {}", e))?;
 
-    match parsed.scheme() {
-        "http" | "https" | "mailto" => {}
-        scheme => {
-            return Err(format!(
-                "URL scheme '{}' is not allowed. Only http, https, and mailto are permitted.",
-                scheme
-            ))
-        }
+    let extra_schemes: Vec<String> = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings
+            .allowed_url_schemes
+            .iter()
+            .flatten()
+            .map(|s| s.trim().trim_end_matches("//").trim_end_matches(':').to_ascii_lowercase())
+            .collect()
+    };
+
+    // Url::parse lowercases the scheme
+    let scheme = parsed.scheme();
+    let allowed = !BLOCKED_URL_SCHEMES.contains(&scheme)
+        && (BUILTIN_URL_SCHEMES.contains(&scheme) || extra_schemes.iter().any(|s| s == scheme));
+
+    if !allowed {
+        let mut permitted: Vec<String> = BUILTIN_URL_SCHEMES.iter().map(|s| s.to_string()).collect();
+        permitted.extend(
+            extra_schemes
+                .into_iter()
+                .filter(|s| !s.is_empty() && !BLOCKED_URL_SCHEMES.contains(&s.as_str())),
+        );
+        return Err(format!(
+            "URL scheme '{}' is not allowed. Only {} are permitted.",
+            scheme,
+            permitted.join(", ")
+        ));
     }
 
     // Use system opener
@@ -1808,6 +7966,26 @@ async fn git_get_status(state: State<'_, AppState>) -> Result<git::GitStatus, St
     }
 }
 
+// Unlike git_get_status this fetches first, so only call it on explicit user action
+#[tauri::command]
+async fn git_remote_status(state: State<'_, AppState>) -> Result<git::GitStatus, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    match folder {
+        Some(path) => {
+            tauri::async_runtime::spawn_blocking(move || {
+                git::remote_status(&PathBuf::from(path))
+            })
+            .await
+            .map_err(|e| e.to_string())
+        }
+        None => Ok(git::GitStatus::default()),
+    }
+}
+
 #[tauri::command]
 async fn git_init_repo(state: State<'_, AppState>) -> Result<(), String> {
     let folder = {
@@ -1828,11 +8006,77 @@ async fn git_commit(message: String, state: State<'_, AppState>) -> Result<git::
         let app_config = state.app_config.read().expect("app_config read lock");
         app_config.notes_folder.clone()
     };
+    let template = commit_message_template(&state);
+
+    match folder {
+        Some(path) => {
+            tauri::async_runtime::spawn_blocking(move || {
+                let path = PathBuf::from(path);
+                let message = if message.trim().is_empty() {
+                    let changed_count = git::get_status(&path).changed_count;
+                    expand_commit_message_template(&template, changed_count)
+                } else {
+                    message
+                };
+                git::commit_all(&path, &message)
+            })
+            .await
+            .map_err(|e| e.to_string())
+        }
+        None => Ok(git::GitResult {
+            success: false,
+            message: None,
+            error: Some("Notes folder not set".to_string()),
+        }),
+    }
+}
+
+#[tauri::command]
+async fn git_stage_note(id: String, state: State<'_, AppState>) -> Result<git::GitResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+    };
+    let root = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&root, &id)?;
+
+    tauri::async_runtime::spawn_blocking(move || git::stage_file(&root, &file_path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_unstage_note(id: String, state: State<'_, AppState>) -> Result<git::GitResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+    };
+    let root = PathBuf::from(&folder);
+    let file_path = abs_path_from_id(&root, &id)?;
+
+    tauri::async_runtime::spawn_blocking(move || git::unstage_file(&root, &file_path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_commit_staged(message: String, state: State<'_, AppState>) -> Result<git::GitResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+    let template = commit_message_template(&state);
 
     match folder {
         Some(path) => {
             tauri::async_runtime::spawn_blocking(move || {
-                git::commit_all(&PathBuf::from(path), &message)
+                let path = PathBuf::from(path);
+                let message = if message.trim().is_empty() {
+                    expand_commit_message_template(&template, git::staged_files(&path).len())
+                } else {
+                    message
+                };
+                git::commit_staged(&path, &message)
             })
             .await
             .map_err(|e| e.to_string())
@@ -1868,6 +8112,48 @@ async fn git_push(state: State<'_, AppState>) -> Result<git::GitResult, String>
     }
 }
 
+#[tauri::command]
+async fn git_push_force_with_lease(state: State<'_, AppState>) -> Result<git::GitResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    match folder {
+        Some(path) => {
+            tauri::async_runtime::spawn_blocking(move || {
+                git::push_force_with_lease(&PathBuf::from(path))
+            })
+            .await
+            .map_err(|e| e.to_string())
+        }
+        None => Ok(git::GitResult {
+            success: false,
+            message: None,
+            error: Some("Notes folder not set".to_string()),
+        }),
+    }
+}
+
+#[tauri::command]
+async fn git_get_remotes(
+    state: State<'_, AppState>,
+) -> Result<BTreeMap<String, git::GitRemote>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    match folder {
+        Some(path) => tauri::async_runtime::spawn_blocking(move || {
+            git::get_remotes(&PathBuf::from(path))
+        })
+        .await
+        .map_err(|e| e.to_string())?,
+        None => Ok(BTreeMap::new()),
+    }
+}
+
 #[tauri::command]
 async fn git_add_remote(url: String, state: State<'_, AppState>) -> Result<git::GitResult, String> {
     let folder = {
@@ -1891,6 +8177,59 @@ async fn git_add_remote(url: String, state: State<'_, AppState>) -> Result<git::
     }
 }
 
+#[tauri::command]
+async fn git_set_remote_url(
+    name: String,
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<git::GitResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    match folder {
+        Some(path) => {
+            tauri::async_runtime::spawn_blocking(move || {
+                git::set_remote_url(&PathBuf::from(path), &name, &url)
+            })
+            .await
+            .map_err(|e| e.to_string())
+        }
+        None => Ok(git::GitResult {
+            success: false,
+            message: None,
+            error: Some("Notes folder not set".to_string()),
+        }),
+    }
+}
+
+#[tauri::command]
+async fn git_remove_remote(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<git::GitResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    match folder {
+        Some(path) => {
+            tauri::async_runtime::spawn_blocking(move || {
+                git::remove_remote(&PathBuf::from(path), &name)
+            })
+            .await
+            .map_err(|e| e.to_string())
+        }
+        None => Ok(git::GitResult {
+            success: false,
+            message: None,
+            error: Some("Notes folder not set".to_string()),
+        }),
+    }
+}
+
 #[tauri::command]
 async fn git_push_with_upstream(state: State<'_, AppState>) -> Result<git::GitResult, String> {
     let folder = {
@@ -1923,6 +8262,90 @@ async fn git_push_with_upstream(state: State<'_, AppState>) -> Result<git::GitRe
     }
 }
 
+#[tauri::command]
+async fn git_list_branches(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone().ok_or("Notes folder not set")?
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        git::list_branches(&PathBuf::from(folder))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn git_checkout_branch(
+    app: AppHandle,
+    branch: String,
+    state: State<'_, AppState>,
+) -> Result<git::GitResult, String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config.notes_folder.clone()
+    };
+
+    let result = match folder {
+        Some(path) => {
+            tauri::async_runtime::spawn_blocking(move || {
+                git::checkout_branch(&PathBuf::from(path), &branch)
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        }
+        None => {
+            return Ok(git::GitResult {
+                success: false,
+                message: None,
+                error: Some("Notes folder not set".to_string()),
+            })
+        }
+    };
+
+    // The working tree changed underneath the index
+    if result.success {
+        rebuild_search_index(app, state).await?;
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+async fn git_clone_vault(
+    app: AppHandle,
+    url: String,
+    dest: String,
+    state: State<'_, AppState>,
+) -> Result<git::GitResult, String> {
+    let dest_path = normalize_notes_folder_path(&dest)?;
+    if dest_path.is_file() {
+        return Err(format!("Destination is a file: {}", dest));
+    }
+    if dest_path.is_dir() {
+        let is_empty = std::fs::read_dir(&dest_path)
+            .map_err(|e| e.to_string())?
+            .next()
+            .is_none();
+        if !is_empty {
+            return Err(format!("Destination folder is not empty: {}", dest));
+        }
+    }
+
+    let clone_dest = dest_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || git::clone(&url, &clone_dest))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Same setup as picking the folder manually: assets dir, settings, index build
+    if result.success {
+        set_notes_folder(app, dest_path.to_string_lossy().into_owned(), None, None, state).await?;
+    }
+
+    Ok(result)
+}
+
 // Check if Claude CLI is installed
 fn get_expanded_path() -> String {
     let system_path = std::env::var("PATH").unwrap_or_default();
@@ -2005,18 +8428,214 @@ async fn ai_check_codex_cli() -> Result<bool, String> {
     .map_err(|e| format!("Failed to check Codex CLI: {}", e))?
 }
 
-/// Shared AI CLI execution: spawns `command` with `args`, writes `stdin_input` to stdin,
-/// and returns the result with a 5-minute timeout.
+/// Record a finished AI run on `file_path` in the vault's AI history. Files outside
+/// the vault aren't recorded.
+fn record_ai_run(
+    state: &AppState,
+    tool: &str,
+    file_path: &str,
+    prompt: &str,
+    result: &AiExecutionResult,
+) {
+    let (_, Some(note_id)) = locate_in_notes_folder(state, Path::new(file_path)) else {
+        return;
+    };
+    let Some(folder) = state
+        .app_config
+        .read()
+        .expect("app_config read lock")
+        .notes_folder
+        .clone()
+    else {
+        return;
+    };
+
+    let text = if result.success {
+        result.output.as_str()
+    } else {
+        result.error.as_deref().unwrap_or_default()
+    };
+    let summary: String = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .chars()
+        .take(AI_HISTORY_SUMMARY_CHARS)
+        .collect();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let entry = AiHistoryEntry {
+        note_id,
+        tool: tool.to_string(),
+        prompt: prompt.to_string(),
+        success: result.success,
+        summary,
+        timestamp,
+    };
+    if let Err(e) = record_ai_history(&folder, entry) {
+        eprintln!("Failed to save AI history: {}", e);
+    }
+}
+
+/// Past AI runs in the current vault, most recent first; only those on `note_id` if given
+#[tauri::command]
+fn get_ai_history(note_id: Option<String>, state: State<AppState>) -> Vec<AiHistoryEntry> {
+    let app_config = state.app_config.read().expect("app_config read lock");
+    let mut history = app_config
+        .notes_folder
+        .as_deref()
+        .map(load_ai_history)
+        .unwrap_or_default();
+    if let Some(note_id) = note_id {
+        history.retain(|entry| entry.note_id == note_id);
+    }
+    history
+}
+
+#[tauri::command]
+fn clear_ai_history(state: State<AppState>) -> Result<(), String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+    let path = get_ai_history_path(&folder);
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Every AI backend with whether its CLI is installed, probing each once. Results are
+/// reused for a short while so the settings UI can call this freely.
+#[tauri::command]
+async fn ai_list_tools(state: State<'_, AppState>) -> Result<Vec<AiTool>, String> {
+    {
+        let cache = state.ai_tools_cache.lock().expect("ai tools cache mutex");
+        if let Some((probed_at, ref tools)) = *cache {
+            if probed_at.elapsed() < AI_TOOLS_TTL {
+                return Ok(tools.clone());
+            }
+        }
+    }
+
+    let tools = tauri::async_runtime::spawn_blocking(|| {
+        let path = get_expanded_path();
+        AI_TOOLS
+            .iter()
+            .map(|(name, command)| AiTool {
+                name: name.to_string(),
+                command: command.to_string(),
+                available: check_cli_exists(command, &path).unwrap_or(false),
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| format!("Failed to check AI CLIs: {}", e))?;
+
+    let mut cache = state.ai_tools_cache.lock().expect("ai tools cache mutex");
+    *cache = Some((Instant::now(), tools.clone()));
+
+    Ok(tools)
+}
+
+// Codex gets no file argument, so by default the prompt is wrapped in instructions
+// naming the file and limiting the edit to it
+const DEFAULT_CODEX_PROMPT_TEMPLATE: &str = "Edit only this markdown file: {file}\n\
+    Apply the user's instructions below directly to that file.\n\
+    Do not create, delete, rename, or modify any other files.\n\
+    User instructions:\n\
+    {prompt}";
+
+/// The text sent to an AI CLI: the aiSystemPrompt setting if set, else `default_template`,
+/// with `{file}` and `{prompt}` filled in. A template without `{prompt}` gets the prompt
+/// appended so it is never dropped.
+fn ai_prompt_input(
+    state: &AppState,
+    default_template: &str,
+    file_path: &str,
+    prompt: &str,
+) -> String {
+    let template = {
+        let settings = state.settings.read().expect("settings read lock");
+        settings
+            .ai_system_prompt
+            .clone()
+            .filter(|t| !t.trim().is_empty())
+    }
+    .unwrap_or_else(|| default_template.to_string());
+
+    let input = template.replace("{file}", file_path);
+    if input.contains("{prompt}") {
+        input.replace("{prompt}", prompt)
+    } else {
+        format!("{}\n\n{}", input.trim_end(), prompt)
+    }
+}
+
+/// Shared AI CLI execution: spawns `command` with `args` in `working_dir` (default: the
+/// note's folder), writes the prompt (wrapped per `default_template` or the aiSystemPrompt
+/// setting) to stdin, and returns the result with a 5-minute timeout. Input over
+/// aiMaxInputBytes is refused unless `allow_large_input` is set.
+#[allow(clippy::too_many_arguments)]
 async fn execute_ai_cli(
+    state: &AppState,
     cli_name: &str,
     command: String,
     args: Vec<String>,
-    stdin_input: String,
+    default_template: &str,
+    file_path: &str,
+    prompt: &str,
+    working_dir: Option<String>,
+    allow_large_input: bool,
     not_found_msg: String,
 ) -> Result<AiExecutionResult, String> {
     use std::io::Write;
     use std::process::{Child, Command, Stdio};
 
+    let stdin_input = ai_prompt_input(state, default_template, file_path, prompt);
+
+    // The CLI reads the note itself, so it counts toward the input along with stdin
+    let max_input_bytes = state
+        .settings
+        .read()
+        .expect("settings read lock")
+        .ai_max_input_bytes
+        .unwrap_or(DEFAULT_AI_MAX_INPUT_BYTES);
+    let input_bytes = stdin_input.len() as u64
+        + std::fs::metadata(file_path).map(|m| m.len()).unwrap_or(0);
+    if !allow_large_input && max_input_bytes > 0 && input_bytes > max_input_bytes {
+        return Ok(AiExecutionResult {
+            success: false,
+            output: String::new(),
+            error: Some(format!(
+                "The note is too large to send to {} ({} KB, limit {} KB). Shorten it, raise aiMaxInputBytes, or run anyway.",
+                cli_name,
+                input_bytes.div_ceil(1024),
+                max_input_bytes / 1024
+            )),
+        });
+    }
+    let working_dir = match working_dir {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            if !dir.is_dir() {
+                return Err(format!("Working directory not found: {}", dir.display()));
+            }
+            Some(dir)
+        }
+        None => Path::new(file_path)
+            .parent()
+            .filter(|dir| dir.is_dir())
+            .map(Path::to_path_buf),
+    };
+
     let cli_name = cli_name.to_string();
     let timeout_duration = std::time::Duration::from_secs(300);
     let shared_child: Arc<Mutex<Option<Child>>> = Arc::new(Mutex::new(None));
@@ -2046,6 +8665,9 @@ async fn execute_ai_cli(
 
         let mut cmd = Command::new(&command);
         cmd.env("PATH", &path);
+        if let Some(dir) = &working_dir {
+            cmd.current_dir(dir);
+        }
         for arg in &args {
             cmd.arg(arg);
         }
@@ -2202,55 +8824,170 @@ async fn execute_ai_cli(
 }
 
 #[tauri::command]
-async fn ai_execute_claude(file_path: String, prompt: String) -> Result<AiExecutionResult, String> {
-    execute_ai_cli(
+async fn ai_execute_claude(
+    file_path: String,
+    prompt: String,
+    working_dir: Option<String>,
+    allow_large_input: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<AiExecutionResult, String> {
+    let result = execute_ai_cli(
+        &state,
         "Claude",
         "claude".to_string(),
         vec![
-            file_path,
+            file_path.clone(),
             "--dangerously-skip-permissions".to_string(),
             "--print".to_string(),
         ],
-        prompt,
+        // Claude gets the file as an argument, so the prompt goes as-is by default
+        "{prompt}",
+        &file_path,
+        &prompt,
+        working_dir,
+        allow_large_input.unwrap_or(false),
         "Claude CLI not found. Please install it from https://claude.ai/code".to_string(),
     )
-    .await
+    .await?;
+    record_ai_run(&state, "claude", &file_path, &prompt, &result);
+    Ok(result)
 }
 
-#[tauri::command]
-async fn ai_execute_codex(file_path: String, prompt: String) -> Result<AiExecutionResult, String> {
-    let stdin_input = format!(
-        "Edit only this markdown file: {file_path}\n\
-         Apply the user's instructions below directly to that file.\n\
-         Do not create, delete, rename, or modify any other files.\n\
-         User instructions:\n\
-         {prompt}"
-    );
+fn codex_args() -> Vec<String> {
+    vec![
+        "exec".to_string(),
+        "--skip-git-repo-check".to_string(),
+        "--dangerously-bypass-approvals-and-sandbox".to_string(),
+        "-".to_string(),
+    ]
+}
 
-    execute_ai_cli(
+const CODEX_NOT_FOUND: &str =
+    "Codex CLI not found. Please install it from https://github.com/openai/codex";
+
+#[tauri::command]
+async fn ai_execute_codex(
+    file_path: String,
+    prompt: String,
+    working_dir: Option<String>,
+    allow_large_input: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<AiExecutionResult, String> {
+    let result = execute_ai_cli(
+        &state,
         "Codex",
         "codex".to_string(),
-        vec![
-            "exec".to_string(),
-            "--skip-git-repo-check".to_string(),
-            "--dangerously-bypass-approvals-and-sandbox".to_string(),
-            "-".to_string(),
-        ],
-        stdin_input,
-        "Codex CLI not found. Please install it from https://github.com/openai/codex".to_string(),
+        codex_args(),
+        DEFAULT_CODEX_PROMPT_TEMPLATE,
+        &file_path,
+        &prompt,
+        working_dir,
+        allow_large_input.unwrap_or(false),
+        CODEX_NOT_FOUND.to_string(),
     )
-    .await
+    .await?;
+    record_ai_run(&state, "codex", &file_path, &prompt, &result);
+    Ok(result)
 }
 
-/// Check if a markdown file is inside the configured notes folder.
-/// If so, emit a "select-note" event to the main window and focus it, returning true.
-/// Returns false on any failure so callers can fall back to create_preview_window.
-fn try_select_in_notes_folder(app: &AppHandle, path: &Path) -> bool {
-    let state = match app.try_state::<AppState>() {
-        Some(s) => s,
-        None => return false,
+/// Run Codex against a temporary copy of the note and return the edit as a unified diff,
+/// leaving the note untouched. Pass the diff to apply_ai_diff to accept it.
+#[tauri::command]
+async fn ai_preview_codex(
+    file_path: String,
+    prompt: String,
+    working_dir: Option<String>,
+    allow_large_input: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<AiDiffPreview, String> {
+    let original_path = PathBuf::from(&file_path);
+    // Non-UTF-8 notes are decoded like read_note; the copy and diff are UTF-8
+    let (original, _) = read_note_text(&original_path).await?;
+    let file_name = original_path
+        .file_name()
+        .ok_or("Invalid file path")?
+        .to_os_string();
+
+    let temp_dir = std::env::temp_dir().join(format!("scratch-ai-preview-{}", random_hex(8)?));
+    fs::create_dir_all(&temp_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Same file name, so the CLI sees a markdown note
+    let temp_path = temp_dir.join(file_name);
+
+    // Run from the temp folder so the tool can't reach the real note by its file name
+    let working_dir = working_dir.or_else(|| Some(temp_dir.to_string_lossy().into_owned()));
+
+    let outcome = match fs::write(&temp_path, &original).await {
+        Ok(()) => {
+            let result = execute_ai_cli(
+                &state,
+                "Codex",
+                "codex".to_string(),
+                codex_args(),
+                DEFAULT_CODEX_PROMPT_TEMPLATE,
+                &temp_path.to_string_lossy(),
+                &prompt,
+                working_dir,
+                allow_large_input.unwrap_or(false),
+                CODEX_NOT_FOUND.to_string(),
+            )
+            .await;
+            match result {
+                Ok(result) => read_note_text(&temp_path)
+                    .await
+                    .map(|(edited, _)| (result, edited)),
+                Err(e) => Err(e),
+            }
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    let _ = fs::remove_dir_all(&temp_dir).await;
+    let (result, edited) = outcome?;
+    record_ai_run(&state, "codex", &file_path, &prompt, &result);
+
+    let diff = if edited == original {
+        String::new()
+    } else {
+        diffy::create_patch(&original, &edited).to_string()
+    };
+    Ok(AiDiffPreview { result, diff })
+}
+
+/// Apply a diff from ai_preview_codex to a note. Fails without writing if the note has
+/// changed so that the diff no longer applies.
+#[tauri::command]
+async fn apply_ai_diff(
+    id: String,
+    diff: String,
+    state: State<'_, AppState>,
+) -> Result<Note, String> {
+    ensure_writable(&state)?;
+
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
     };
 
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+    let (content, _) = read_note_text(&file_path).await?;
+
+    let patch = diffy::Patch::from_str(&diff).map_err(|e| format!("Invalid diff: {}", e))?;
+    let patched = diffy::apply(&content, &patch)
+        .map_err(|_| "The note has changed since the preview; the diff no longer applies".to_string())?;
+
+    rewrite_note(&state, id, &file_path, patched).await
+}
+
+/// Locate an existing path relative to the notes folder after resolving symlinks.
+/// Returns (inside_vault, note_id); note_id is only set for markdown notes.
+fn locate_in_notes_folder(state: &AppState, path: &Path) -> (bool, Option<String>) {
     let notes_folder = state
         .app_config
         .read()
@@ -2260,25 +8997,89 @@ fn try_select_in_notes_folder(app: &AppHandle, path: &Path) -> bool {
 
     let folder = match notes_folder {
         Some(f) => f,
-        None => return false,
+        None => return (false, None),
     };
 
     let folder_path = PathBuf::from(&folder);
+
+    // A note reached through a symlinked folder in the vault canonicalizes to somewhere
+    // outside it, so first take the path as given when it's plainly under the vault
+    let lexically_inside = path.is_absolute()
+        && path.starts_with(&folder_path)
+        && !path.components().any(|c| matches!(c, std::path::Component::ParentDir));
+    if lexically_inside && path.exists() {
+        return (true, id_from_abs_path(&folder_path, path));
+    }
+
     let (canonical_file, canonical_folder) = match (path.canonicalize(), folder_path.canonicalize())
     {
         (Ok(f), Ok(d)) => (f, d),
-        _ => return false,
+        _ => return (false, None),
     };
 
     if !canonical_file.starts_with(&canonical_folder) {
-        return false;
+        return (false, None);
+    }
+
+    (true, id_from_abs_path(&canonical_folder, &canonical_file))
+}
+
+// Result of classify_path, used by the frontend to decide how to handle a dropped path
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathClassification {
+    pub is_markdown: bool,
+    pub inside_vault: bool,
+    pub note_id: Option<String>,
+    pub exists: bool,
+}
+
+#[tauri::command]
+fn classify_path(path: String, state: State<AppState>) -> PathClassification {
+    let path = PathBuf::from(path);
+    let (inside_vault, note_id) = locate_in_notes_folder(&state, &path);
+
+    PathClassification {
+        is_markdown: is_markdown_extension(&path),
+        inside_vault,
+        note_id,
+        exists: path.exists(),
     }
+}
+
+// Result of is_inside_vault
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultMembership {
+    pub inside_vault: bool,
+    pub note_id: Option<String>,
+}
+
+/// Whether an existing path is inside the active vault after resolving symlinks, with
+/// its note ID if it's a markdown note. False when no vault is set.
+#[tauri::command]
+fn is_inside_vault(path: String, state: State<AppState>) -> VaultMembership {
+    let (inside_vault, note_id) = locate_in_notes_folder(&state, Path::new(&path));
+    VaultMembership {
+        inside_vault,
+        note_id,
+    }
+}
 
-    let note_id = match id_from_abs_path(&canonical_folder, &canonical_file) {
-        Some(id) => id,
+/// Check if a markdown file is inside the configured notes folder.
+/// If so, emit a "select-note" event to the main window and focus it, returning true.
+/// Returns false on any failure so callers can fall back to create_preview_window.
+fn try_select_in_notes_folder(app: &AppHandle, path: &Path) -> bool {
+    let state = match app.try_state::<AppState>() {
+        Some(s) => s,
         None => return false,
     };
 
+    let note_id = match locate_in_notes_folder(&state, path) {
+        (true, Some(id)) => id,
+        _ => return false,
+    };
+
     let _ = app.emit_to("main", "select-note", note_id);
     if let Some(main_window) = app.get_webview_window("main") {
         let _ = main_window.set_focus();
@@ -2302,10 +9103,31 @@ fn create_preview_window(app: &AppHandle, file_path: &str) -> Result<(), String>
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
 
+    // Key the window on the canonical path so symlinks and differently-cased paths to
+    // the same file reuse one window. Fall back to the raw path if the file just vanished.
+    let mut window_key = PathBuf::from(file_path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| file_path.to_string());
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        window_key = window_key.to_lowercase();
+    }
+
     let mut hasher = DefaultHasher::new();
-    file_path.hash(&mut hasher);
+    window_key.hash(&mut hasher);
     let label = format!("preview-{:x}", hasher.finish());
 
+    // Allow save_file_direct to write this file even though it may be outside the vault
+    if let (Some(state), Ok(canonical)) =
+        (app.try_state::<AppState>(), PathBuf::from(file_path).canonicalize())
+    {
+        state
+            .preview_files
+            .lock()
+            .expect("preview files mutex")
+            .insert(canonical);
+    }
+
     // If window already exists for this file, focus it
     if let Some(window) = app.get_webview_window(&label) {
         window.set_focus().map_err(|e| e.to_string())?;
@@ -2362,7 +9184,86 @@ fn open_file_preview(app: AppHandle, path: String) -> Result<(), String> {
     Ok(())
 }
 
+/// Open a vault note in its own preview window instead of selecting it in the main window
+#[tauri::command]
+fn open_note_in_new_window(app: AppHandle, id: String, state: State<AppState>) -> Result<(), String> {
+    let folder = {
+        let app_config = state.app_config.read().expect("app_config read lock");
+        app_config
+            .notes_folder
+            .clone()
+            .ok_or("Notes folder not set")?
+    };
+
+    let file_path = abs_path_from_id(&PathBuf::from(&folder), &id)?;
+    if !file_path.exists() {
+        return Err("Note not found".to_string());
+    }
+
+    create_preview_window(&app, &file_path.to_string_lossy())
+}
+
 // Handle CLI arguments: open .md files in preview mode
+// How open_files handled a path: selected in the main window, opened in a preview
+// window, or rejected (not an existing markdown file, or the window failed to open)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpenFileOutcome {
+    Selected,
+    Preview,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenFileResult {
+    pub path: String,
+    pub outcome: OpenFileOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Select an existing markdown file in the main window if it's inside the vault,
+/// otherwise open it in a preview window.
+fn open_markdown_path(app: &AppHandle, path: &Path) -> Result<OpenFileOutcome, String> {
+    if try_select_in_notes_folder(app, path) {
+        return Ok(OpenFileOutcome::Selected);
+    }
+    create_preview_window(app, &path.to_string_lossy())?;
+    Ok(OpenFileOutcome::Preview)
+}
+
+/// Open a list of files the same way CLI arguments are opened. Paths must be absolute.
+#[tauri::command]
+fn open_files(app: AppHandle, paths: Vec<String>) -> Vec<OpenFileResult> {
+    paths
+        .into_iter()
+        .map(|p| {
+            let path = PathBuf::from(&p);
+            let result = if !path.is_absolute() {
+                Err("Path must be absolute".to_string())
+            } else if !is_markdown_extension(&path) || !path.is_file() {
+                Err("Not a markdown file".to_string())
+            } else {
+                open_markdown_path(&app, &path)
+            };
+
+            match result {
+                Ok(outcome) => OpenFileResult {
+                    path: p,
+                    outcome,
+                    error: None,
+                },
+                Err(e) => OpenFileResult {
+                    path: p,
+                    outcome: OpenFileOutcome::Rejected,
+                    error: Some(e),
+                },
+            }
+        })
+        .collect()
+}
+
 fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) {
     let mut opened_file = false;
 
@@ -2380,9 +9281,7 @@ fn handle_cli_args(app: &AppHandle, args: &[String], cwd: &str) {
 
         if is_markdown_extension(&path) && path.is_file() {
             opened_file = true;
-            if !try_select_in_notes_folder(app, &path) {
-                let _ = create_preview_window(app, &path.to_string_lossy());
-            }
+            let _ = open_markdown_path(app, &path);
         }
     }
 
@@ -2439,12 +9338,34 @@ pub fn run() {
                 Settings::default()
             };
 
-            // Initialize search index if notes folder is set
-            let search_index = if let Some(ref folder) = app_config.notes_folder {
-                if let Ok(index_path) = get_search_index_path(app.handle()) {
-                    SearchIndex::new(&index_path).ok().inspect(|idx| {
-                        let _ = idx.rebuild_index(&PathBuf::from(folder));
-                    })
+            // Claim the vault; if another instance has it, writes are refused and
+            // vault_lock_status tells the UI so it can offer a takeover
+            let vault_locked = app_config
+                .notes_folder
+                .as_deref()
+                .filter(|folder| Path::new(folder).is_dir())
+                .is_some_and(|folder| acquire_vault_lock(folder, false).is_ok());
+
+            // Initialize search index if notes folder is set and this instance holds it
+            let search_index = if let Some(folder) =
+                app_config.notes_folder.as_ref().filter(|_| vault_locked)
+            {
+                if let Ok(index_path) = get_search_index_path(app.handle(), folder) {
+                    SearchIndex::new(
+                        &index_path,
+                        index_writer_buffer_bytes(&settings),
+                        search_text_analyzer(&settings),
+                        index_source_root(&settings, folder),
+                    )
+                        .ok()
+                        .inspect(|idx| {
+                            let _ = idx.rebuild_index(
+                                &PathBuf::from(folder),
+                                note_scan(&settings),
+                                text_lengths(&settings).title_fallback,
+                                None,
+                            );
+                        })
                 } else {
                     None
                 }
@@ -2459,6 +9380,13 @@ pub fn run() {
                 file_watcher: Mutex::new(None),
                 search_index: Mutex::new(search_index),
                 debounce_map: Arc::new(Mutex::new(HashMap::new())),
+                vault_stats_cache: Mutex::new(None),
+                ai_tools_cache: Mutex::new(None),
+                last_operation: Mutex::new(None),
+                served_hashes: Mutex::new(HashMap::new()),
+                preview_files: Mutex::new(HashSet::new()),
+                preview_watchers: Mutex::new(HashMap::new()),
+                self_writes: Mutex::new(HashMap::new()),
             };
             app.manage(state);
 
@@ -2475,34 +9403,100 @@ pub fn run() {
             Ok(())
         })
         .on_window_event(|window, event| {
+            // Stop watching a preview window's file once the window is gone
+            if let tauri::WindowEvent::Destroyed = event {
+                if let Some(state) = window.app_handle().try_state::<AppState>() {
+                    state
+                        .preview_watchers
+                        .lock()
+                        .expect("preview watchers mutex")
+                        .remove(window.label());
+                }
+            }
             // Handle drag-and-drop of .md files onto any window
             if let tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) = event {
                 let app = window.app_handle();
                 for path in paths {
-                    if is_markdown_extension(path)
-                        && path.is_file()
-                        && !try_select_in_notes_folder(app, path)
-                    {
-                        let _ = create_preview_window(app, &path.to_string_lossy());
+                    if is_markdown_extension(path) && path.is_file() {
+                        let _ = open_markdown_path(app, path);
                     }
                 }
             }
         })
         .invoke_handler(tauri::generate_handler![
             get_notes_folder,
+            get_app_paths,
+            set_search_index_location,
             set_notes_folder,
+            get_recent_vaults,
+            open_recent_vault,
             list_notes,
+            note_neighbors,
+            suggest,
+            vault_stats,
+            note_hash,
+            note_modified,
+            vault_hashes,
+            export_metadata_csv,
+            export_note_bundle,
+            find_duplicate_titles,
+            list_orphan_notes,
+            list_conflicted_notes,
+            resolve_conflict,
+            link_graph,
+            term_frequency,
+            list_tasks,
+            toggle_task,
+            reload_note,
+            convert_note_to_utf8,
+            vault_disk_space,
+            resolve_asset_path,
+            list_note_assets,
+            get_vault_health,
             read_note,
+            read_note_range,
+            get_frontmatter,
+            set_frontmatter_field,
+            rename_tag,
+            render_markdown,
             save_note,
             delete_note,
+            split_note,
+            note_outline,
+            append_under_heading,
+            convert_links,
+            convert_all_links,
             create_note,
+            quick_capture,
+            rename_folder,
+            delete_folder,
+            undo_last_operation,
+            normalize_filenames,
+            preview_rename_all,
+            apply_rename_all,
             get_settings,
             update_settings,
+            reload_settings,
+            get_read_only,
+            set_last_opened_note,
             preview_note_name,
+            validate_note_template,
             write_file,
             search_notes,
+            search_prefix,
+            find_notes_by_prefix,
+            get_recent_searches,
+            add_recent_search,
+            clear_recent_searches,
+            search_all_vaults,
             start_file_watcher,
+            vault_available,
+            vault_lock_status,
+            revalidate_vault,
             rebuild_search_index,
+            repair_search_index,
+            verify_index,
+            reload_search_reader,
             copy_to_clipboard,
             copy_image_to_assets,
             save_clipboard_image,
@@ -2511,25 +9505,61 @@ pub fn run() {
             open_url_safe,
             git_is_available,
             git_get_status,
+            git_remote_status,
+            git_stage_note,
+            git_unstage_note,
+            git_commit_staged,
             git_init_repo,
             git_commit,
             git_push,
+            git_push_force_with_lease,
+            git_get_remotes,
             git_add_remote,
+            git_set_remote_url,
+            git_remove_remote,
             git_push_with_upstream,
+            git_list_branches,
+            git_checkout_branch,
+            git_clone_vault,
             ai_check_claude_cli,
             ai_check_codex_cli,
+            ai_list_tools,
+            get_ai_history,
+            clear_ai_history,
             ai_execute_claude,
             ai_execute_codex,
+            ai_preview_codex,
+            apply_ai_diff,
             read_file_direct,
             save_file_direct,
+            watch_external_file,
             open_file_preview,
+            open_note_in_new_window,
+            open_files,
+            classify_path,
+            is_inside_vault,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
 
-    // Use .run() callback to handle macOS "Open With" file events
-    // RunEvent::Opened is macOS-only in Tauri v2
+    // Use .run() callback to flush the index and release the vault lock on exit, and to
+    // handle macOS "Open With" file events (RunEvent::Opened is macOS-only in Tauri v2)
     app.run(|_app_handle, _event| {
+        // Leave search accurate, and let other instances open the vault once this one quits
+        if let tauri::RunEvent::ExitRequested { .. } = &_event {
+            flush_search_index(_app_handle);
+            let folder = _app_handle
+                .state::<AppState>()
+                .app_config
+                .read()
+                .expect("app_config read lock")
+                .notes_folder
+                .clone();
+            if let Some(folder) = folder {
+                release_vault_lock(&folder);
+            }
+        }
+
         #[cfg(target_os = "macos")]
         if let tauri::RunEvent::Opened { urls } = _event {
             for url in urls {
@@ -2545,3 +9575,267 @@ pub fn run() {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_memory_index(settings: &Settings) -> SearchIndex {
+        let index = Index::create_in_ram(SearchIndex::build_schema(true));
+        SearchIndex::from_index(
+            index,
+            Some(MIN_INDEX_WRITER_BUFFER_MB * 1_000_000),
+            search_text_analyzer(settings),
+            None,
+        )
+        .expect("in-memory index")
+    }
+
+    fn notes(ids: &[&str]) -> Vec<(String, String)> {
+        ids.iter()
+            .map(|id| (id.to_string(), format!("# {}", id)))
+            .collect()
+    }
+
+    #[test]
+    fn plan_renames_keeps_folders_and_skips_notes_already_named() {
+        let notes = notes(&["done", "work/Draft", "work/todo"]);
+        let plan = plan_renames(&notes, CollisionStrategy::Numeric, |id, _| {
+            let leaf = id.rsplit('/').next().unwrap();
+            if leaf == "done" {
+                leaf.to_string()
+            } else {
+                leaf.to_uppercase()
+            }
+        });
+
+        let renames: Vec<(&str, &str)> = plan
+            .iter()
+            .map(|p| (p.old_id.as_str(), p.new_id.as_str()))
+            .collect();
+        // Case-only renames don't collide with the note itself
+        assert_eq!(
+            renames,
+            [("work/Draft", "work/DRAFT"), ("work/todo", "work/TODO")]
+        );
+        assert!(plan.iter().all(|p| !p.collision));
+    }
+
+    #[test]
+    fn plan_renames_suffixes_names_that_are_taken() {
+        let notes = notes(&["Alpha", "a", "b"]);
+        let plan = plan_renames(&notes, CollisionStrategy::Numeric, |id, _| {
+            if id == "a" { "alpha" } else { "Alpha" }.to_string()
+        });
+
+        let renames: Vec<(&str, &str, bool)> = plan
+            .iter()
+            .map(|p| (p.old_id.as_str(), p.new_id.as_str(), p.collision))
+            .collect();
+        // Names collide case-insensitively, with existing notes and earlier renames
+        assert_eq!(renames, [("a", "alpha-1", true), ("b", "Alpha-2", true)]);
+    }
+
+    #[test]
+    fn templated_leaf_replaces_only_the_title_part() {
+        let template = "{date}-{title}";
+        assert_eq!(
+            templated_leaf(template, "2024-01-15-Old Title", "New Title").as_deref(),
+            Some("2024-01-15-New Title")
+        );
+        // An unchanged title keeps the leaf, collision suffix included
+        assert_eq!(
+            templated_leaf(template, "2024-01-15-Same-2", "Same").as_deref(),
+            Some("2024-01-15-Same-2")
+        );
+        assert_eq!(templated_leaf(template, "Not templated", "Title"), None);
+        assert_eq!(
+            templated_leaf("{date}", "2024-01-15", "Anything").as_deref(),
+            Some("2024-01-15")
+        );
+    }
+
+    #[test]
+    fn rename_tag_in_content_renames_inline_and_frontmatter_tags() {
+        let content = "---\ntags: [work, Work/Projects, home]\n---\n\
+            #work and #work/notes but not #workshop or `#work`\n";
+        let (renamed, count) = rename_tag_in_content(content, "work", "job");
+
+        assert_eq!(count, 4);
+        let (raw, body_offset) = split_frontmatter(&renamed).unwrap();
+        assert_eq!(
+            parse_frontmatter_yaml(raw).unwrap()["tags"],
+            serde_json::json!(["job", "job/Projects", "home"])
+        );
+        assert_eq!(
+            &renamed[body_offset..],
+            "#job and #job/notes but not #workshop or `#work`\n"
+        );
+    }
+
+    #[test]
+    fn rename_tag_in_content_leaves_unrelated_notes_alone() {
+        let content = "---\ntitle: Plan\n---\n#home only\n";
+        assert_eq!(
+            rename_tag_in_content(content, "work", "job"),
+            (content.to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn split_sections_ignores_other_levels_and_fenced_code() {
+        let content = "Intro\n## A\ntext a\n```\n## not a heading\n```\n### Sub\n## B ##\nb\n";
+        let (preamble, sections) = split_sections(content, 2);
+
+        assert_eq!(preamble, "Intro\n");
+        assert_eq!(
+            sections,
+            [
+                (
+                    "A".to_string(),
+                    "text a\n```\n## not a heading\n```\n### Sub\n"
+                ),
+                ("B".to_string(), "b\n"),
+            ]
+        );
+        assert_eq!(split_sections("No headings\n", 2).1.len(), 0);
+    }
+
+    #[test]
+    fn take_rename_source_pairs_by_tracker() {
+        let now = Instant::now();
+        let pending = Mutex::new(HashMap::from([
+            (
+                PathBuf::from("/vault/a.md"),
+                PendingRename {
+                    at: now - Duration::from_millis(50),
+                    tracker: Some(1),
+                },
+            ),
+            (
+                PathBuf::from("/vault/b.md"),
+                PendingRename {
+                    at: now,
+                    tracker: Some(2),
+                },
+            ),
+        ]));
+
+        assert_eq!(
+            take_rename_source(&pending, Some(1)),
+            Some(PathBuf::from("/vault/a.md"))
+        );
+        assert_eq!(take_rename_source(&pending, Some(3)), None);
+        assert_eq!(pending.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn take_rename_source_without_tracker_takes_the_latest_in_the_window() {
+        let now = Instant::now();
+        let pending = Mutex::new(HashMap::from([
+            (
+                PathBuf::from("/vault/expired.md"),
+                PendingRename {
+                    at: now - RENAME_PAIR_WINDOW * 5,
+                    tracker: None,
+                },
+            ),
+            (
+                PathBuf::from("/vault/older.md"),
+                PendingRename {
+                    at: now - Duration::from_millis(20),
+                    tracker: None,
+                },
+            ),
+            (
+                PathBuf::from("/vault/latest.md"),
+                PendingRename {
+                    at: now - Duration::from_millis(10),
+                    tracker: None,
+                },
+            ),
+        ]));
+
+        assert_eq!(
+            take_rename_source(&pending, None),
+            Some(PathBuf::from("/vault/latest.md"))
+        );
+        assert_eq!(
+            take_rename_source(&pending, None),
+            Some(PathBuf::from("/vault/older.md"))
+        );
+        // Expired entries are left for the sweeper
+        assert_eq!(take_rename_source(&pending, None), None);
+    }
+
+    #[test]
+    fn numeric_collision_suffix_is_the_attempt_number() {
+        assert_eq!(collision_suffix(CollisionStrategy::Numeric, "body", 1), "1");
+        assert_eq!(collision_suffix(CollisionStrategy::Numeric, "body", 7), "7");
+    }
+
+    #[test]
+    fn short_hash_collision_suffix_grows_on_each_attempt() {
+        let hash = content_hash(b"body");
+        let first = collision_suffix(CollisionStrategy::ShortHash, "body", 1);
+        let second = collision_suffix(CollisionStrategy::ShortHash, "body", 2);
+        assert_eq!(first, hash[..6]);
+        assert_eq!(second, hash[..8]);
+        assert!(is_collision_variant(&format!("Note-{}", first), "Note"));
+
+        // Past the full hash the attempt number is appended
+        let last = collision_suffix(CollisionStrategy::ShortHash, "body", 40);
+        assert_eq!(last, format!("{}-40", hash));
+        assert!(is_collision_variant(&format!("Note-{}", last), "Note"));
+    }
+
+    #[test]
+    fn note_name_template_expands_date_and_time_tags() {
+        let name = expand_note_name_template("{year}-{month}-{day} {time} {counter}");
+        let (date, rest) = name.split_once(' ').unwrap();
+        let (time, counter) = rest.split_once(' ').unwrap();
+
+        assert!(chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok());
+        assert!(chrono::NaiveTime::parse_from_str(time, "%H-%M-%S").is_ok());
+        // {counter} is left for create_note
+        assert_eq!(counter, "{counter}");
+        assert_eq!(expand_note_name_template("Plain name"), "Plain name");
+    }
+
+    #[test]
+    fn utf8_note_bytes_are_kept_as_is() {
+        let (text, encoding) = decode_note_bytes("Grüße ✓".as_bytes().to_vec());
+        assert_eq!(text, "Grüße ✓");
+        assert_eq!(encoding, None);
+    }
+
+    #[test]
+    fn latin1_note_bytes_are_decoded_from_the_detected_encoding() {
+        let text = "Le café était très crémeux à Noël, déjà prêt pour la fête.";
+        let bytes: Vec<u8> = text.chars().map(|c| c as u32 as u8).collect();
+
+        let (decoded, encoding) = decode_note_bytes(bytes);
+        assert_eq!(decoded, text);
+        assert!(encoding.is_some());
+    }
+
+    #[test]
+    fn cjk_search_matches_substring_of_a_note() {
+        let settings = Settings {
+            search_language: Some("japanese".to_string()),
+            ..Settings::default()
+        };
+        let index = in_memory_index(&settings);
+        index
+            .index_note("tokyo", "東京の天気", "今日の東京の天気は晴れです", 0)
+            .unwrap();
+        index.reload_reader().unwrap();
+
+        let lengths = text_lengths(&settings);
+        let results = index.search("天気", 10, false, lengths).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "tokyo");
+
+        assert!(index.search("大阪", 10, false, lengths).unwrap().is_empty());
+    }
+}
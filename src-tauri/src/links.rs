@@ -0,0 +1,481 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// `[[target]]`, `[[target|label]]`, `[[target#heading]]`
+    Wiki,
+    /// `[label](relative/path.md)`
+    Markdown,
+}
+
+/// A link written in a note's source
+#[derive(Debug, Clone)]
+pub struct NoteLink {
+    pub kind: LinkKind,
+    /// Link target without label, heading fragment, or angle brackets (percent-decoded)
+    pub target: String,
+    /// Byte range of the whole link in the content
+    pub span: Range<usize>,
+    /// Byte range of the raw target as written (before decoding, without fragment)
+    pub target_span: Range<usize>,
+    /// Heading fragment after `#`, as written
+    pub fragment: Option<String>,
+    /// `[[target|label]]` label, or the text of a markdown link
+    pub label: Option<String>,
+}
+
+impl NoteLink {
+    /// Whether this link points at a note (as opposed to an attachment like a PDF)
+    pub fn is_note_link(&self) -> bool {
+        match self.kind {
+            LinkKind::Wiki => true,
+            LinkKind::Markdown => self.target.to_ascii_lowercase().ends_with(".md"),
+        }
+    }
+}
+
+/// Extract wikilinks and relative markdown links from note content, in document order.
+/// Links inside fenced code blocks, images, and external URLs are skipped.
+pub fn extract_links(content: &str) -> Vec<NoteLink> {
+    let wiki_re =
+        regex::Regex::new(r"\[\[([^\[\]|#]+)(?:#([^\[\]|]*))?(?:\|([^\[\]]*))?\]\]").unwrap();
+    let md_re =
+        regex::Regex::new(r#"\[([^\]]*)\]\(\s*(<[^>]+>|[^)\s]+)(?:\s+"[^"]*")?\s*\)"#).unwrap();
+    let fences = crate::fenced_code_ranges(content);
+    let in_fence = |pos: usize| fences.iter().any(|r| r.contains(&pos));
+
+    let mut links = Vec::new();
+
+    for caps in wiki_re.captures_iter(content) {
+        let whole = caps.get(0).unwrap();
+        if in_fence(whole.start()) {
+            continue;
+        }
+        let raw = caps.get(1).unwrap();
+        let target = raw.as_str().trim();
+        if !target.is_empty() {
+            let target_start = raw.start() + (raw.as_str().len() - raw.as_str().trim_start().len());
+            links.push(NoteLink {
+                kind: LinkKind::Wiki,
+                target: target.to_string(),
+                span: whole.range(),
+                target_span: target_start..target_start + target.len(),
+                fragment: caps.get(2).map(|m| m.as_str().to_string()),
+                label: caps.get(3).map(|m| m.as_str().to_string()),
+            });
+        }
+    }
+
+    for caps in md_re.captures_iter(content) {
+        let whole = caps.get(0).unwrap();
+        // Skip images (![alt](src)) and the inner part of wikilink-looking text
+        if whole.start() > 0 && content[..whole.start()].ends_with(['!', '[']) {
+            continue;
+        }
+        if in_fence(whole.start()) {
+            continue;
+        }
+
+        let raw_match = caps.get(2).unwrap();
+        let raw = raw_match.as_str().trim_start_matches('<').trim_end_matches('>');
+        if is_external_link(raw) {
+            continue;
+        }
+        let (without_fragment, fragment) = match raw.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment.to_string())),
+            None => (raw, None),
+        };
+        let target_start = raw_match.start() + usize::from(raw_match.as_str().starts_with('<'));
+        if without_fragment.is_empty() {
+            continue;
+        }
+        let target = urlencoding::decode(without_fragment)
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| without_fragment.to_string());
+
+        links.push(NoteLink {
+            kind: LinkKind::Markdown,
+            target,
+            span: whole.range(),
+            target_span: target_start..target_start + without_fragment.len(),
+            fragment,
+            label: Some(caps.get(1).unwrap().as_str().to_string()),
+        });
+    }
+
+    links.sort_by_key(|link| link.span.start);
+    links
+}
+
+/// URLs with a scheme (http:, mailto:, obsidian://, ...) or protocol-relative URLs
+fn is_external_link(target: &str) -> bool {
+    if crate::is_external_ref(target) || target.contains("://") {
+        return true;
+    }
+    // "scheme:rest" where scheme is purely alphabetic (mailto:, tel:, ...)
+    match target.split_once(':') {
+        Some((scheme, _)) => !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphabetic()),
+        None => false,
+    }
+}
+
+/// Resolves link targets to note IDs for a snapshot of the vault.
+#[derive(Debug, Default)]
+pub struct LinkResolver {
+    ids: HashSet<String>,
+    // Lowercased file name or title -> note ID (first registered note wins)
+    by_name: HashMap<String, String>,
+    // Lowercased frontmatter alias -> note ID, consulted after file names and titles
+    by_alias: HashMap<String, String>,
+}
+
+impl LinkResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_note(&mut self, id: &str, title: &str) {
+        self.ids.insert(id.to_string());
+        let leaf = id.rsplit('/').next().unwrap_or(id).to_lowercase();
+        self.by_name.entry(leaf).or_insert_with(|| id.to_string());
+        self.by_name
+            .entry(title.to_lowercase())
+            .or_insert_with(|| id.to_string());
+    }
+
+    pub fn add_alias(&mut self, id: &str, alias: &str) {
+        self.by_alias
+            .entry(alias.trim().to_lowercase())
+            .or_insert_with(|| id.to_string());
+    }
+
+    /// Resolve a wikilink target (an ID, file name, title, or alias) to a note ID
+    pub fn resolve_name(&self, name: &str) -> Option<String> {
+        let target = name.trim();
+        let target = target.strip_suffix(".md").unwrap_or(target);
+        if self.ids.contains(target) {
+            return Some(target.to_string());
+        }
+        let target = target.to_lowercase();
+        self.by_name
+            .get(&target)
+            .or_else(|| self.by_alias.get(&target))
+            .cloned()
+    }
+
+    /// Resolve a link found in `source_id` to the ID of an existing note.
+    pub fn resolve(&self, notes_root: &Path, source_id: &str, link: &NoteLink) -> Option<String> {
+        match link.kind {
+            LinkKind::Wiki => self.resolve_name(&link.target),
+            LinkKind::Markdown => {
+                if !link.is_note_link() {
+                    return None;
+                }
+                // Relative to the linking note first, then to the vault root
+                let from_note = crate::resolve_note_relative_path(notes_root, source_id, &link.target)
+                    .ok()
+                    .and_then(|p| crate::id_from_abs_path(notes_root, &p));
+                let from_root = || {
+                    let rel = link.target.trim_start_matches('/');
+                    crate::id_from_abs_path(notes_root, &notes_root.join(rel))
+                };
+                from_note
+                    .or_else(from_root)
+                    .filter(|id| self.ids.contains(id))
+            }
+        }
+    }
+}
+
+/// Rewrite links in `content` (from note `source_id`) whose targets were renamed.
+/// `renames` maps old note IDs to new ones; `resolver` must describe the vault before the
+/// renames. Returns None when no link changed.
+pub fn rewrite_links(
+    notes_root: &Path,
+    source_id: &str,
+    content: &str,
+    resolver: &LinkResolver,
+    renames: &HashMap<String, String>,
+) -> Option<String> {
+    let mut result = content.to_string();
+    let mut changed = false;
+
+    // Replace back to front so earlier spans stay valid
+    for link in extract_links(content).iter().rev() {
+        let new_id = match resolver
+            .resolve(notes_root, source_id, link)
+            .and_then(|old_id| renames.get(&old_id))
+        {
+            Some(new_id) => new_id,
+            None => continue,
+        };
+
+        let replacement = match link.kind {
+            // Keep the link style: full path stays a full path, bare names stay bare
+            LinkKind::Wiki if link.target.contains('/') => new_id.clone(),
+            LinkKind::Wiki => new_id.rsplit('/').next().unwrap_or(new_id).to_string(),
+            LinkKind::Markdown => {
+                let rel = relative_note_path(source_id, new_id);
+                let in_angle_brackets = content[..link.target_span.start].ends_with('<');
+                if in_angle_brackets {
+                    rel
+                } else {
+                    encode_link_destination(&rel)
+                }
+            }
+        };
+
+        result.replace_range(link.target_span.clone(), &replacement);
+        changed = true;
+    }
+
+    changed.then_some(result)
+}
+
+/// Rewrite the note links in `content` (from note `source_id`) into the `to` style:
+/// `[[target#heading|label]]` <-> `[label](relative/path.md#heading)`. Markdown links get
+/// the label, or else the target's title from `titles`; wikilinks use the bare file name
+/// when that resolves back to the same note, else the full ID. Unresolvable links and
+/// external URLs are left alone. Returns None when no link changed.
+pub fn convert_links(
+    notes_root: &Path,
+    source_id: &str,
+    content: &str,
+    resolver: &LinkResolver,
+    titles: &HashMap<String, String>,
+    to: LinkKind,
+) -> Option<String> {
+    let mut result = content.to_string();
+    let mut changed = false;
+
+    // Replace back to front so earlier spans stay valid
+    for link in extract_links(content).iter().rev() {
+        if link.kind == to {
+            continue;
+        }
+        let id = match resolver.resolve(notes_root, source_id, link) {
+            Some(id) => id,
+            None => continue,
+        };
+        let label = link.label.as_deref().map(str::trim).filter(|l| !l.is_empty());
+
+        let replacement = match to {
+            LinkKind::Markdown => {
+                let text = label
+                    .map(str::to_string)
+                    .or_else(|| titles.get(&id).cloned())
+                    .unwrap_or_else(|| link.target.clone())
+                    .replace('[', "\\[")
+                    .replace(']', "\\]");
+                let mut path = encode_link_destination(&relative_note_path(source_id, &id));
+                if let Some(ref fragment) = link.fragment {
+                    path = format!("{}#{}", path, encode_link_destination(fragment.trim()));
+                }
+                format!("[{}]({})", text, path)
+            }
+            LinkKind::Wiki => {
+                // Wikilink syntax can't carry these characters in a label
+                if label.is_some_and(|l| l.contains(['|', '[', ']'])) {
+                    continue;
+                }
+                let leaf = id.rsplit('/').next().unwrap_or(&id);
+                let target = if resolver.resolve_name(leaf).as_deref() == Some(id.as_str()) {
+                    leaf
+                } else {
+                    id.as_str()
+                };
+                let mut wiki = format!("[[{}", target);
+                if let Some(ref fragment) = link.fragment {
+                    let fragment = urlencoding::decode(fragment)
+                        .map(|s| s.into_owned())
+                        .unwrap_or_else(|_| fragment.clone());
+                    wiki.push('#');
+                    wiki.push_str(&fragment);
+                }
+                // Drop the label when it just repeats the target or its title
+                let redundant = |l: &str| {
+                    l.eq_ignore_ascii_case(target)
+                        || titles.get(&id).is_some_and(|t| t.eq_ignore_ascii_case(l))
+                };
+                if let Some(label) = label.filter(|l| !redundant(l)) {
+                    wiki.push('|');
+                    wiki.push_str(label);
+                }
+                wiki.push_str("]]");
+                wiki
+            }
+        };
+
+        result.replace_range(link.span.clone(), &replacement);
+        changed = true;
+    }
+
+    changed.then_some(result)
+}
+
+/// Percent-encode the characters that would end or break a bare markdown link
+/// destination: whitespace, parentheses, and angle brackets. `%` is encoded too so the
+/// result decodes back to `path`.
+pub fn encode_link_destination(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '%' => encoded.push_str("%25"),
+            ' ' => encoded.push_str("%20"),
+            '\t' => encoded.push_str("%09"),
+            '(' => encoded.push_str("%28"),
+            ')' => encoded.push_str("%29"),
+            '<' => encoded.push_str("%3C"),
+            '>' => encoded.push_str("%3E"),
+            _ => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Path of note `to_id`'s file relative to the folder containing note `from_id`
+pub fn relative_note_path(from_id: &str, to_id: &str) -> String {
+    let from_dir: Vec<&str> = match from_id.rsplit_once('/') {
+        Some((dir, _)) => dir.split('/').collect(),
+        None => Vec::new(),
+    };
+    let to_parts: Vec<&str> = to_id.split('/').collect();
+
+    let common = from_dir
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to_parts.len() - 1);
+
+    let mut parts: Vec<&str> = vec![".."; from_dir.len() - common];
+    parts.extend(&to_parts[common..]);
+    format!("{}.md", parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wikilink_resolves_through_alias() {
+        let mut resolver = LinkResolver::new();
+        resolver.add_note("projects/foo-bar", "Foo Bar");
+        resolver.add_alias("projects/foo-bar", " Foo ");
+
+        assert_eq!(
+            resolver.resolve_name("foo").as_deref(),
+            Some("projects/foo-bar")
+        );
+
+        let links = extract_links("See [[foo]] for details");
+        assert_eq!(links.len(), 1);
+        assert_eq!(
+            resolver
+                .resolve(Path::new("/vault"), "index", &links[0])
+                .as_deref(),
+            Some("projects/foo-bar")
+        );
+    }
+
+    #[test]
+    fn converted_markdown_links_encode_parentheses() {
+        let mut resolver = LinkResolver::new();
+        resolver.add_note("Meeting (draft)", "Meeting (draft)");
+        let titles = HashMap::new();
+
+        let converted = convert_links(
+            Path::new("/vault"),
+            "index",
+            "See [[Meeting (draft)]]",
+            &resolver,
+            &titles,
+            LinkKind::Markdown,
+        )
+        .unwrap();
+        assert_eq!(converted, "See [Meeting (draft)](Meeting%20%28draft%29.md)");
+
+        let links = extract_links(&converted);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target, "Meeting (draft).md");
+    }
+
+    #[test]
+    fn extract_links_skips_code_images_and_external_urls() {
+        let content = "[[Alpha|first]] and [Beta](notes/Beta%20Two.md#Intro)\n\
+            ![diagram](img.png) [site](https://example.com) [mail](mailto:a@b.c)\n\
+            ```\n[[Inside code]]\n```\n";
+        let links = extract_links(content);
+        assert_eq!(links.len(), 2);
+
+        assert_eq!(links[0].kind, LinkKind::Wiki);
+        assert_eq!(links[0].target, "Alpha");
+        assert_eq!(links[0].label.as_deref(), Some("first"));
+
+        assert_eq!(links[1].kind, LinkKind::Markdown);
+        assert_eq!(links[1].target, "notes/Beta Two.md");
+        assert_eq!(links[1].fragment.as_deref(), Some("Intro"));
+        assert_eq!(
+            &content[links[1].target_span.clone()],
+            "notes/Beta%20Two.md"
+        );
+    }
+
+    #[test]
+    fn relative_note_path_walks_up_to_the_common_folder() {
+        assert_eq!(relative_note_path("index", "a/b"), "a/b.md");
+        assert_eq!(relative_note_path("a/b", "c"), "../c.md");
+        assert_eq!(relative_note_path("a/b/c", "a/d"), "../d.md");
+        assert_eq!(relative_note_path("a/b", "a/c"), "c.md");
+    }
+
+    #[test]
+    fn rewrite_links_keeps_the_link_style() {
+        let mut resolver = LinkResolver::new();
+        resolver.add_note("old", "Old");
+        resolver.add_note("folder/index", "Index");
+        let renames = HashMap::from([("old".to_string(), "archive/new name".to_string())]);
+
+        let rewritten = rewrite_links(
+            Path::new("/vault"),
+            "folder/index",
+            "[[old]] [[old#Top|label]] [x](../old.md)",
+            &resolver,
+            &renames,
+        )
+        .unwrap();
+        assert_eq!(
+            rewritten,
+            "[[new name]] [[new name#Top|label]] [x](../archive/new%20name.md)"
+        );
+
+        assert!(rewrite_links(
+            Path::new("/vault"),
+            "folder/index",
+            "[[Index]]",
+            &resolver,
+            &renames
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn encoded_destinations_decode_back() {
+        let path = "100% (final) <v2>.md";
+        let encoded = encode_link_destination(path);
+        assert_eq!(encoded, "100%25%20%28final%29%20%3Cv2%3E.md");
+        assert_eq!(urlencoding::decode(&encoded).unwrap(), path);
+    }
+
+    #[test]
+    fn file_names_and_titles_win_over_aliases() {
+        let mut resolver = LinkResolver::new();
+        resolver.add_note("foo", "Foo");
+        resolver.add_note("other", "Other");
+        resolver.add_alias("other", "foo");
+
+        assert_eq!(resolver.resolve_name("foo").as_deref(), Some("foo"));
+    }
+}
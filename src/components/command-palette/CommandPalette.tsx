@@ -459,15 +459,16 @@ export function CommandPalette({
     // Debounce search calls
     const timer = setTimeout(async () => {
       try {
-        const results = await invoke<
-          {
+        const { results } = await invoke<{
+          results: {
             id: string;
             title: string;
             preview: string;
             modified: number;
             score: number;
-          }[]
-        >("search_notes", { query: trimmed });
+          }[];
+          degraded: boolean;
+        }>("search_notes", { query: trimmed });
         setLocalSearchResults(results);
       } catch (err) {
         console.error("Search failed:", err);
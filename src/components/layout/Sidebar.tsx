@@ -1,5 +1,6 @@
 import { useCallback, useEffect, useRef, useState } from "react";
 import { useNotes } from "../../context/NotesContext";
+import * as notesService from "../../services/notes";
 import { NoteList } from "../notes/NoteList";
 import { Footer } from "./Footer";
 import { IconButton, Input } from "../ui";
@@ -80,6 +81,12 @@ export function Sidebar({ onOpenSettings }: SidebarProps) {
 
   const handleSearchKeyDown = useCallback(
     (e: React.KeyboardEvent<HTMLInputElement>) => {
+      if (e.key === "Enter" && inputValue.trim()) {
+        notesService.addRecentSearch(inputValue).catch((err) =>
+          console.error("Failed to save recent search:", err)
+        );
+        return;
+      }
       if (e.key === "Escape") {
         e.preventDefault();
         if (inputValue) {
@@ -119,6 +119,7 @@ export function NoteList() {
     isLoading,
     searchQuery,
     searchResults,
+    vaultUnavailable,
   } = useNotes();
 
   const [deleteDialogOpen, setDeleteDialogOpen] = useState(false);
@@ -240,6 +241,14 @@ export function NoteList() {
     );
   }
 
+  if (vaultUnavailable) {
+    return (
+      <div className="p-4 text-center text-sm text-text-muted select-none">
+        Notes folder unavailable. Waiting for it to reconnect...
+      </div>
+    );
+  }
+
   if (searchQuery.trim() && displayItems.length === 0) {
     return (
       <div className="p-4 text-center text-sm text-text-muted select-none">
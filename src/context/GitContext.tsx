@@ -185,10 +185,10 @@ export function GitProvider({ children }: { children: ReactNode }) {
   // Refresh status on file changes (debounced via existing file watcher)
   // Uses a ref so the listener is registered only once
   useEffect(() => {
-    let unlisten: (() => void) | undefined;
+    const unlisteners: (() => void)[] = [];
     let debounceTimer: number | undefined;
 
-    listen("file-change", () => {
+    const onChange = () => {
       // Debounce git status refresh to avoid excessive calls
       if (debounceTimer) {
         clearTimeout(debounceTimer);
@@ -196,12 +196,13 @@ export function GitProvider({ children }: { children: ReactNode }) {
       debounceTimer = window.setTimeout(() => {
         refreshStatusRef.current();
       }, 1000);
-    }).then((fn) => {
-      unlisten = fn;
-    });
+    };
+    // Commands emit "file-change"; the watcher sends batched "files-changed"
+    listen("file-change", onChange).then((fn) => unlisteners.push(fn));
+    listen("files-changed", onChange).then((fn) => unlisteners.push(fn));
 
     return () => {
-      if (unlisten) unlisten();
+      unlisteners.forEach((fn) => fn());
       if (debounceTimer) clearTimeout(debounceTimer);
     };
   }, []);
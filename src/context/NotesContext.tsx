@@ -9,6 +9,7 @@ import {
   type ReactNode,
 } from "react";
 import { listen } from "@tauri-apps/api/event";
+import { ask } from "@tauri-apps/plugin-dialog";
 import type { Note, NoteMetadata } from "../types/note";
 import * as notesService from "../services/notes";
 import type { SearchResult } from "../services/notes";
@@ -27,6 +28,8 @@ interface NotesDataContextValue {
   isSearching: boolean;
   hasExternalChanges: boolean;
   reloadVersion: number;
+  // The notes folder is missing (e.g. an unmounted drive); cleared when it comes back
+  vaultUnavailable: boolean;
 }
 
 // Actions context: stable references, rarely causes re-renders
@@ -61,6 +64,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
   const [hasExternalChanges, setHasExternalChanges] = useState(false);
   // Increments when user manually refreshes, so Editor knows to reload content
   const [reloadVersion, setReloadVersion] = useState(0);
+  const [vaultUnavailable, setVaultUnavailable] = useState(false);
 
   // Track recently saved note IDs to ignore file-change events from our own saves
   const recentlySavedRef = useRef<Set<string>>(new Set());
@@ -80,7 +84,12 @@ export function NotesProvider({ children }: { children: ReactNode }) {
     try {
       const notesList = await notesService.listNotes();
       setNotes(notesList);
+      setVaultUnavailable(false);
     } catch (err) {
+      if (err === notesService.VAULT_UNAVAILABLE) {
+        setVaultUnavailable(true);
+        return;
+      }
       setError(err instanceof Error ? err.message : "Failed to load notes");
     }
   }, [notesFolder]);
@@ -196,7 +205,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
           if (updatedId) recentlySavedRef.current.delete(updatedId);
         }, 1000);
       } catch (err) {
-        setError(err instanceof Error ? err.message : "Failed to save note");
+        setError(notesService.noteErrorMessage(err, "Failed to save note"));
         // Clean up immediately on error to avoid leaving stale entries
         recentlySavedRef.current.delete(savingNoteId);
         if (updatedId) recentlySavedRef.current.delete(updatedId);
@@ -250,7 +259,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
           recentlySavedRef.current.delete(newNote.id);
         }, 1000);
       } catch (err) {
-        setError(err instanceof Error ? err.message : "Failed to duplicate note");
+        setError(notesService.noteErrorMessage(err, "Failed to duplicate note"));
       }
     },
     [refreshNotes]
@@ -380,12 +389,27 @@ export function NotesProvider({ children }: { children: ReactNode }) {
         const folder = await notesService.getNotesFolder();
         setNotesFolderState(folder);
         if (folder) {
+          // Another instance has this vault open; it stays read-only here unless taken over
+          const lock = await notesService.vaultLockStatus();
+          if (lock.heldByOther && lock.holder) {
+            const takeOver = await ask(
+              `This notes folder is open in another instance of Scratch (${lock.holder.hostname}, process ${lock.holder.pid}). Take it over here?`,
+              { title: "Notes folder in use", kind: "warning" }
+            );
+            if (takeOver) {
+              await notesService.setNotesFolder(folder, true);
+            }
+          }
           const notesList = await notesService.listNotes();
           setNotes(notesList);
           // Start file watcher
           await notesService.startFileWatcher();
         }
       } catch (err) {
+        if (err === notesService.VAULT_UNAVAILABLE) {
+          setVaultUnavailable(true);
+          return;
+        }
         setError(err instanceof Error ? err.message : "Failed to initialize");
       } finally {
         setIsLoading(false);
@@ -394,16 +418,23 @@ export function NotesProvider({ children }: { children: ReactNode }) {
     init();
   }, []);
 
-  // Listen for file change events and notify if current note changed externally
+  // Listen for file change events and notify if current note changed externally.
+  // Commands emit "file-change" per change; the watcher batches into "files-changed".
   useEffect(() => {
     let isCancelled = false;
-    let unlisten: (() => void) | undefined;
+    const unlisteners: (() => void)[] = [];
 
-    listen<{ changed_ids: string[] }>("file-change", (event) => {
+    const handleChanges = (changedIds: string[], renamed: [string, string][]) => {
       // Don't process if effect was cleaned up
       if (isCancelled) return;
 
-      const changedIds = event.payload.changed_ids || [];
+      // Follow the selected note when it's renamed on disk
+      const currentRename = renamed.find(([oldId]) => oldId === selectedNoteIdRef.current);
+      if (currentRename) {
+        refreshNotes();
+        selectNote(currentRename[1]);
+        return;
+      }
 
       // Filter out notes we recently saved ourselves
       const externalChanges = changedIds.filter(
@@ -420,22 +451,40 @@ export function NotesProvider({ children }: { children: ReactNode }) {
           setHasExternalChanges(true);
         }
       }
-    }).then((fn) => {
-      if (isCancelled) {
-        // Effect was cleaned up before listener registered, clean up immediately
-        fn();
-      } else {
-        unlisten = fn;
-      }
-    });
+    };
+
+    const register = (unlistenPromise: Promise<() => void>) => {
+      unlistenPromise.then((fn) => {
+        if (isCancelled) {
+          // Effect was cleaned up before listener registered, clean up immediately
+          fn();
+        } else {
+          unlisteners.push(fn);
+        }
+      });
+    };
+
+    register(
+      listen<{ kind: string; changed_ids: string[] }>("file-change", (event) => {
+        const changedIds = event.payload.changed_ids || [];
+        const renamed: [string, string][] =
+          event.payload.kind === "renamed" && changedIds.length === 2
+            ? [[changedIds[0], changedIds[1]]]
+            : [];
+        handleChanges(changedIds, renamed);
+      })
+    );
+    register(
+      listen<{ changed_ids: string[]; renamed: [string, string][] }>("files-changed", (event) => {
+        handleChanges(event.payload.changed_ids || [], event.payload.renamed || []);
+      })
+    );
 
     return () => {
       isCancelled = true;
-      if (unlisten) {
-        unlisten();
-      }
+      unlisteners.forEach((fn) => fn());
     };
-  }, [refreshNotes]);
+  }, [refreshNotes, selectNote]);
 
   // Listen for "select-note" events from the backend (CLI, drag-drop, Open With for notes-folder files)
   useEffect(() => {
@@ -447,6 +496,24 @@ export function NotesProvider({ children }: { children: ReactNode }) {
     };
   }, [selectNote]);
 
+  // While the vault is missing, poll until it's back; the backend restarts the watcher
+  // and rebuilds the index when it reappears
+  useEffect(() => {
+    if (!vaultUnavailable) return;
+    const interval = window.setInterval(async () => {
+      try {
+        if (await notesService.vaultAvailable()) {
+          setVaultUnavailable(false);
+          setError(null);
+          refreshNotes();
+        }
+      } catch (err) {
+        console.error("Vault availability check failed:", err);
+      }
+    }, 5000);
+    return () => clearInterval(interval);
+  }, [vaultUnavailable, refreshNotes]);
+
   // Refresh notes when folder changes
   useEffect(() => {
     if (notesFolder) {
@@ -468,6 +535,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
       isSearching,
       hasExternalChanges,
       reloadVersion,
+      vaultUnavailable,
     }),
     [
       notes,
@@ -481,6 +549,7 @@ export function NotesProvider({ children }: { children: ReactNode }) {
       isSearching,
       hasExternalChanges,
       reloadVersion,
+      vaultUnavailable,
     ]
   );
 
@@ -14,8 +14,9 @@ export async function readFileDirect(path: string): Promise<FileContent> {
 export async function saveFileDirect(
   path: string,
   content: string,
+  allowExternal = false,
 ): Promise<FileContent> {
-  return invoke("save_file_direct", { path, content });
+  return invoke("save_file_direct", { path, content, allowExternal });
 }
 
 export async function openFilePreview(path: string): Promise<void> {
@@ -7,7 +7,9 @@ export interface GitStatus {
   remoteUrl: string | null;
   changedCount: number;
   aheadCount: number;
+  behindCount: number;
   currentBranch: string | null;
+  stagedFiles: string[];
   error: string | null;
 }
 
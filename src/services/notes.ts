@@ -5,14 +5,35 @@ export async function getNotesFolder(): Promise<string | null> {
   return invoke("get_notes_folder");
 }
 
-export async function setNotesFolder(path: string): Promise<void> {
-  return invoke("set_notes_folder", { path });
+export async function setNotesFolder(
+  path: string,
+  forceTakeover?: boolean
+): Promise<void> {
+  return invoke("set_notes_folder", { path, forceTakeover });
+}
+
+// Which app instance holds the open vault's lock (see set_notes_folder's forceTakeover)
+export interface VaultLockStatus {
+  heldByOther: boolean;
+  holder: { pid: number; hostname: string; acquiredAt: number } | null;
+}
+
+export async function vaultLockStatus(): Promise<VaultLockStatus> {
+  return invoke("vault_lock_status");
 }
 
 export async function listNotes(): Promise<NoteMetadata[]> {
   return invoke("list_notes");
 }
 
+// Error string list_notes/read_note reject with when the notes folder is missing
+// (e.g. an unmounted drive), as opposed to an empty vault
+export const VAULT_UNAVAILABLE = "VaultUnavailable";
+
+export async function vaultAvailable(): Promise<boolean> {
+  return invoke("vault_available");
+}
+
 export async function readNote(id: string): Promise<Note> {
   return invoke("read_note", { id });
 }
@@ -21,6 +42,26 @@ export async function saveNote(id: string | null, content: string): Promise<Note
   return invoke("save_note", { id, content });
 }
 
+// save_note rejects with one of these objects rather than a plain string
+export type NoteError =
+  | { kind: "conflict"; expectedModified: number; diskModified: number | null }
+  | { kind: "other"; message: string };
+
+export function isNoteError(err: unknown): err is NoteError {
+  return typeof err === "object" && err !== null && "kind" in err;
+}
+
+// Message to show for a rejected save_note (or any other command's string error)
+export function noteErrorMessage(err: unknown, fallback: string): string {
+  if (isNoteError(err)) {
+    return err.kind === "conflict"
+      ? "This note was changed on disk since it was opened"
+      : err.message;
+  }
+  if (typeof err === "string") return err;
+  return err instanceof Error ? err.message : fallback;
+}
+
 export async function deleteNote(id: string): Promise<void> {
   return invoke("delete_note", { id });
 }
@@ -54,8 +95,20 @@ export interface SearchResult {
   score: number;
 }
 
+export interface SearchResponse {
+  results: SearchResult[];
+  // True when results came from the substring fallback instead of the index
+  degraded: boolean;
+}
+
 export async function searchNotes(query: string): Promise<SearchResult[]> {
-  return invoke("search_notes", { query });
+  const response = await invoke<SearchResponse>("search_notes", { query });
+  return response.results;
+}
+
+// Remember a submitted query in the vault's recent searches
+export async function addRecentSearch(query: string): Promise<void> {
+  return invoke("add_recent_search", { query });
 }
 
 export async function startFileWatcher(): Promise<void> {